@@ -17,6 +17,7 @@ fn build() -> std::result::Result<(), Box<dyn std::error::Error>> {
                 match ext.to_string_lossy().as_ref() {
                     "vert" => Some(glsl::transpiler::spirv::ShaderKind::Vertex),
                     "frag" => Some(glsl::transpiler::spirv::ShaderKind::Fragment),
+                    "comp" => Some(glsl::transpiler::spirv::ShaderKind::Compute),
                     _ => None,
                 }
             });
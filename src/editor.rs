@@ -0,0 +1,76 @@
+pub struct Editor {
+    selected: crate::voxel::Block,
+    target: Option<crate::voxel::RayHit>,
+}
+
+impl Editor {
+    const REACH: f32 = 64.;
+
+    const SELECT_KEYS: [winit::event::VirtualKeyCode; 9] = [
+        winit::event::VirtualKeyCode::Key1,
+        winit::event::VirtualKeyCode::Key2,
+        winit::event::VirtualKeyCode::Key3,
+        winit::event::VirtualKeyCode::Key4,
+        winit::event::VirtualKeyCode::Key5,
+        winit::event::VirtualKeyCode::Key6,
+        winit::event::VirtualKeyCode::Key7,
+        winit::event::VirtualKeyCode::Key8,
+        winit::event::VirtualKeyCode::Key9,
+    ];
+
+    pub fn new(registry: &crate::voxel::BlockRegistry) -> Self {
+        Self {
+            selected: Self::selectable(registry).next().unwrap_or(1),
+            target: None,
+        }
+    }
+
+    // The blocks the number keys select, in order. Only blocks defined in the registry can be
+    // placed, so gaps in the IDs are skipped.
+    fn selectable(
+        registry: &crate::voxel::BlockRegistry,
+    ) -> impl Iterator<Item = crate::voxel::Block> + '_ {
+        registry
+            .iter()
+            .map(|(block, _)| block)
+            .filter(|&block| block != crate::voxel::BlockRegistry::AIR)
+    }
+
+    pub fn update(
+        &mut self,
+        state: &crate::window::State,
+        camera: &crate::render::Camera,
+        object: &mut crate::voxel::Object,
+        registry: &crate::voxel::BlockRegistry,
+    ) {
+        for (&key, block) in Self::SELECT_KEYS.iter().zip(Self::selectable(registry)) {
+            if state.key_pressed(key) {
+                self.selected = block;
+                log::info!("Selected {}", registry.get(block).name)
+            }
+        }
+
+        self.target = object.raycast(&camera.ray(), Self::REACH, registry);
+
+        if let Some(target) = self.target {
+            if state.mouse_pressed(winit::event::MouseButton::Left) {
+                object.set_block(target.voxel, crate::voxel::BlockRegistry::AIR)
+            } else if state.mouse_pressed(winit::event::MouseButton::Right)
+                && target.normal != [0; 3]
+            {
+                object.set_block(target.adjacent(), self.selected)
+            }
+        }
+    }
+
+    // The voxel the camera is aimed at, as of the last update.
+    pub fn target(&self) -> Option<[i32; 3]> {
+        self.target.map(|target| target.voxel)
+    }
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new(&crate::voxel::BlockRegistry::builtin())
+    }
+}
@@ -0,0 +1,173 @@
+// Golden-image regression checks for the voxel renderer.
+//
+// Each scene is rendered offscreen from a fixed camera and compared against `<name>.ppm` in the
+// reference directory. Run against a software Vulkan driver (e.g. lavapipe) so the output doesn't
+// depend on the GPU, and regenerate the references with `--bless` after intended changes.
+
+struct Scene {
+    name: &'static str,
+    object: fn() -> crate::voxel::Object,
+    camera: crate::render::Camera,
+}
+
+const SIZE: (u32, u32) = (256, 192);
+
+// A pixel counts as different when a channel is off by more than this...
+const TOLERANCE: u8 = 8;
+// ...and a scene fails when more than this fraction of its pixels are different.
+const MAX_DIFFERING: f64 = 0.001;
+
+fn scenes() -> Vec<Scene> {
+    fn single(chunk: fn() -> crate::voxel::Chunk) -> crate::voxel::Object {
+        let mut object = crate::voxel::Object::new();
+        object.insert_chunk(crate::voxel::ChunkCoord::new(0, 0, 0), chunk());
+        object
+    }
+
+    vec![
+        Scene {
+            name: "test1",
+            object: || single(crate::voxel::Chunk::test1),
+            camera: crate::render::Camera::new(
+                ultraviolet::Vec3::new(-24., -20., 40.),
+                0.7,
+                -0.5,
+            ),
+        },
+        Scene {
+            name: "test2",
+            object: || single(crate::voxel::Chunk::test2),
+            camera: crate::render::Camera::new(
+                ultraviolet::Vec3::new(-24., -20., 48.),
+                0.7,
+                -0.6,
+            ),
+        },
+        Scene {
+            name: "test2-inside",
+            object: || single(crate::voxel::Chunk::test2),
+            camera: crate::render::Camera::new(
+                ultraviolet::Vec3::new(16., 16., 16.),
+                0.,
+                0.,
+            ),
+        },
+        Scene {
+            name: "hills",
+            object: || crate::voxel::Object::hills(2),
+            camera: crate::render::Camera::new(
+                ultraviolet::Vec3::new(-60., -60., 30.),
+                0.785,
+                -0.35,
+            ),
+        },
+    ]
+}
+
+// Renders every scene and checks it against its reference, or overwrites the references when
+// `bless` is set. With `cpu`, scenes are rendered by the software renderer instead of Vulkan on
+// `device`. Returns whether all scenes passed.
+pub fn run(
+    dir: &std::path::Path,
+    bless: bool,
+    cpu: bool,
+    device: Option<&crate::render::DeviceSelector>,
+) -> bool {
+    let blocks = crate::voxel::BlockRegistry::builtin();
+    let textures = crate::render::block_textures(&blocks);
+    let strategy = crate::voxel::MeshStrategy::default();
+    let sun = crate::render::Sun::default();
+    let mut passed = true;
+
+    if bless {
+        std::fs::create_dir_all(dir).unwrap();
+    }
+
+    for scene in scenes() {
+        let mut object = (scene.object)();
+        let image = if cpu {
+            let (width, height) = SIZE;
+            object.update_light(&blocks);
+            crate::render::cpu::render(
+                &object,
+                &blocks,
+                &textures,
+                &scene.camera,
+                &sun,
+                crate::VERTICAL_FOV,
+                width,
+                height,
+            )
+        } else {
+            match crate::screenshot(
+                &mut object,
+                &blocks,
+                &textures,
+                strategy,
+                &scene.camera,
+                &sun,
+                SIZE,
+                device,
+            ) {
+                Ok(image) => image,
+                Err(e) => {
+                    log::error!("{}: {}", scene.name, e);
+                    passed = false;
+                    continue;
+                }
+            }
+        };
+        let reference_path = dir.join(format!("{}.ppm", scene.name));
+
+        if bless {
+            match image.save(&reference_path) {
+                Ok(()) => log::info!("{}: wrote {:?}", scene.name, reference_path),
+                Err(e) => {
+                    log::error!("{}: failed to write {:?}: {}", scene.name, reference_path, e);
+                    passed = false
+                }
+            }
+            continue;
+        }
+
+        let failure = match crate::render::Image::load_ppm(&reference_path) {
+            Err(e) => Some(format!("failed to read {:?}: {}", reference_path, e)),
+            Ok(reference) => match image.diff(&reference, TOLERANCE) {
+                None => Some(format!(
+                    "size is {}x{}, reference is {}x{}",
+                    image.width(),
+                    image.height(),
+                    reference.width(),
+                    reference.height()
+                )),
+                Some(diff) if diff.ratio() > MAX_DIFFERING => {
+                    let diff_path = dir.join(format!("{}.diff.ppm", scene.name));
+                    if let Err(e) = diff.image.save(&diff_path) {
+                        log::warn!("{}: failed to write {:?}: {}", scene.name, diff_path, e)
+                    }
+                    Some(format!(
+                        "{} pixels ({:.3}%) differ, by up to {}",
+                        diff.differing,
+                        diff.ratio() * 100.,
+                        diff.max_delta
+                    ))
+                }
+                Some(_) => None,
+            },
+        };
+
+        match failure {
+            None => log::info!("{}: ok", scene.name),
+            Some(message) => {
+                let actual_path = dir.join(format!("{}.actual.ppm", scene.name));
+                if let Err(e) = image.save(&actual_path) {
+                    log::warn!("{}: failed to write {:?}: {}", scene.name, actual_path, e)
+                }
+                log::error!("{}: {}", scene.name, message);
+                passed = false
+            }
+        }
+    }
+
+    passed
+}
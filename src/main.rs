@@ -1,41 +1,461 @@
 #[macro_use] mod shaders;
+mod editor;
+mod golden;
 mod render;
 mod voxel;
 mod window;
 
 const DEBUG_MODE: bool = cfg!(debug_assertions);
+const VERTICAL_FOV: f32 = 45.;
 
 fn main() {
-    let chunk = voxel::Chunk::test1();
+    let args = Args::parse();
 
     env_logger::builder()
         .filter_level(log::LevelFilter::max())
         .init();
 
-    let window = window::Window::new();
+    let mut blocks = match std::env::var_os("VOXELITE_BLOCKS") {
+        Some(path) => open_or_exit(&path, voxel::BlockRegistry::load(&path)),
+        None => voxel::BlockRegistry::builtin(),
+    };
+    let mut object = match args.scene {
+        Some(path) if path.to_string_lossy().ends_with(".vox") => {
+            let vox = open_or_exit(&path, voxel::Vox::load_file(&path));
+            blocks = vox.registry();
+            vox.object
+        }
+        Some(path) => open_or_exit(&path, voxel::Object::load_file(&path)),
+        None => voxel::Object::hills(3),
+    };
+
+    let mut strategy = env_or("VOXELITE_MESHER", voxel::MeshStrategy::default());
+
+    let mut sun = env_or("VOXELITE_SUN", render::Sun::default());
+
+    if args.list_devices {
+        for adapter in or_exit(render::Adapter::list()) {
+            println!("{}", adapter);
+            for (name, value) in adapter.capabilities() {
+                println!("  {}: {}", name, value)
+            }
+        }
+        return;
+    }
+
+    let device = match args.device {
+        Some(device) => Some(device),
+        None => std::env::var("VOXELITE_DEVICE")
+            .ok()
+            .map(|value| render::DeviceSelector::parse(&value)),
+    };
+
+    let textures = render::block_textures(&blocks);
 
-    let instance = render::Instance::new(&window);
-    let mut surface = render::Surface::new(instance.clone(), &window);
-    let mut renderer = render::Renderer::new(&surface);
-    let mut voxel_renderer = render::VoxelRenderer::new(&surface, &chunk.faces());
+    if let Some(dir) = args.golden {
+        if !golden::run(&dir, args.bless, args.cpu, device.as_ref()) {
+            std::process::exit(1)
+        }
+        return;
+    }
+
+    if let Some(path) = args.screenshot {
+        let image = if args.cpu {
+            let (width, height) = args.size;
+            object.update_light(&blocks);
+            render::cpu::render(
+                &object,
+                &blocks,
+                &textures,
+                &args.camera,
+                &sun,
+                VERTICAL_FOV,
+                width,
+                height,
+            )
+        } else {
+            or_exit(screenshot(
+                &mut object,
+                &blocks,
+                &textures,
+                strategy,
+                &args.camera,
+                &sun,
+                args.size,
+                device.as_ref(),
+            ))
+        };
+        image.save(&path).unwrap();
+        log::info!("Saved screenshot to {:?}", path);
+        return;
+    }
+
+    let mut window = window::Window::new();
+    window.set_max_fps(std::env::var("VOXELITE_MAX_FPS").ok().and_then(|max_fps| {
+        max_fps
+            .parse()
+            .map_err(|e| log::warn!("Ignoring VOXELITE_MAX_FPS: {}", e))
+            .ok()
+    }));
+
+    let mut culling = env_or("VOXELITE_CULLING", render::Culling::Cpu);
+    let mut present_mode = env_or("VOXELITE_PRESENT_MODE", render::PresentMode::default());
+    let mut gpu = Some(or_exit(Gpu::new(
+        &window,
+        device.as_ref(),
+        &textures,
+        culling,
+        present_mode,
+    )));
+    let mut editor = editor::Editor::new(&blocks);
 
-    let mut camera = render::PlayerCamera::new(ultraviolet::Vec3::new(-5., 0., 0.), 0., 0.);
+    let mut camera = render::PlayerCamera::from(args.camera);
+    let mut frame_times = (0, std::time::Duration::default());
+    let mut chunk_counts = render::ChunkCounts::default();
+    let mut log_faces = true;
+    let scene_path = std::env::var_os("VOXELITE_SCENE").unwrap_or_else(|| "scene.vxl".into());
 
     window.run(move |state, window| {
         if state.quit() {
-            instance.wait_idle();
+            // A lost device has nothing left to wait for.
+            if let Some(gpu) = &gpu {
+                gpu.instance.wait_idle().ok();
+            }
             return;
         }
 
+        // The device was lost last frame, so start over with a new one and upload every chunk
+        // again.
+        if gpu.is_none() {
+            log::info!("Recreating the renderer");
+            gpu = Some(or_exit(Gpu::new(
+                window,
+                device.as_ref(),
+                &textures,
+                culling,
+                present_mode,
+            )));
+            object.mark_all_dirty();
+            log_faces = true
+        }
+        let Gpu {
+            instance,
+            surface,
+            renderer,
+            voxel_renderer,
+            outline_renderer,
+        } = gpu.as_mut().unwrap();
+
+        frame_times.0 += 1;
+        frame_times.1 += state.frame_elapsed();
+        if frame_times.1.as_secs() >= 1 {
+            log::info!(
+                "{:?} meshing: {:.2} ms/frame, {} chunks drawn, {} culled",
+                strategy,
+                frame_times.1.as_secs_f64() * 1000. / frame_times.0 as f64,
+                chunk_counts.drawn,
+                chunk_counts.culled
+            );
+            frame_times = Default::default();
+        }
+
+        if state.key_pressed(winit::event::VirtualKeyCode::M) {
+            strategy = strategy.next();
+            object.mark_all_dirty();
+            log_faces = true
+        }
+        if state.key_pressed(winit::event::VirtualKeyCode::C) {
+            voxel_renderer.set_culling(voxel_renderer.culling().next());
+            culling = voxel_renderer.culling();
+            log::info!("{:?} culling", culling)
+        }
+        if state.key_pressed(winit::event::VirtualKeyCode::V) {
+            present_mode = present_mode.next();
+            surface.set_present_mode(present_mode);
+            log::info!("{:?} presentation", present_mode)
+        }
+
+        if state.key_pressed(winit::event::VirtualKeyCode::F5) {
+            match object.save_file(&scene_path) {
+                Ok(()) => log::info!("Saved scene to {:?}", scene_path),
+                Err(e) => log::error!("Failed to save {:?}: {}", scene_path, e),
+            }
+        }
+        if state.key_pressed(winit::event::VirtualKeyCode::F6) {
+            let path = std::path::Path::new(&scene_path).with_extension("vox");
+            match voxel::Vox::save_file(&object, &blocks.palette(), &path) {
+                Ok(()) => log::info!("Exported scene to {:?}", path),
+                Err(e) => log::error!("Failed to export {:?}: {}", path, e),
+            }
+        }
+        if state.key_pressed(winit::event::VirtualKeyCode::F9) {
+            match voxel::Object::load_file(&scene_path) {
+                Ok(loaded) => {
+                    log::info!("Loaded scene from {:?}", scene_path);
+                    object = loaded;
+                    voxel_renderer.clear();
+                    log_faces = true
+                }
+                Err(e) => log::error!("Failed to load {:?}: {}", scene_path, e),
+            }
+        }
+
         camera.update(state);
-        let matrix = camera.matrix(45., surface.aspect_ratio());
+        sun.update(state);
+        editor.update(state, camera.camera(), &mut object, &blocks);
+
+        let result = remesh_dirty(voxel_renderer, &mut object, &blocks, strategy).and_then(|()| {
+            if log_faces {
+                log::info!(
+                    "{:?} meshing: {} faces",
+                    strategy,
+                    voxel_renderer.face_count()
+                );
+                log::info!("GPU memory: {}", instance.memory_stats());
+                log_faces = false
+            }
+
+            let matrix = camera.matrix(VERTICAL_FOV, surface.aspect_ratio());
+            voxel_renderer.prepare(&sun, &matrix)?;
 
-        if !renderer.render(&mut surface, |command_buffer| {
-            voxel_renderer.draw(command_buffer, &matrix)
-        }) {
-            instance.wait_idle();
-            surface.rebuild(window);
-            voxel_renderer.rebuild(&surface, &chunk.faces())
+            if !renderer.render(
+                surface,
+                |command_buffer| voxel_renderer.prepass(command_buffer),
+                |command_buffer| {
+                    chunk_counts = voxel_renderer.draw(command_buffer);
+                    if let Some(target) = editor.target() {
+                        outline_renderer.draw(command_buffer, &matrix, target)
+                    }
+                },
+            )? {
+                instance.wait_idle()?;
+                surface.rebuild(window)?;
+                voxel_renderer.rebuild(surface)?;
+                outline_renderer.rebuild(surface)?
+            }
+            Ok(())
+        });
+        match result {
+            Ok(()) => (),
+            // Everything is recreated next frame. The scene itself is only on the CPU, so no
+            // edits are lost.
+            Err(render::RenderError::DeviceLost) => {
+                log::error!("The GPU device was lost");
+                gpu = None
+            }
+            Err(e) => or_exit(Err(e)),
         }
     });
 }
+
+// Everything on the GPU, which has to be recreated together if the device is lost.
+struct Gpu {
+    voxel_renderer: render::VoxelRenderer,
+    outline_renderer: render::OutlineRenderer,
+    renderer: render::Renderer,
+    surface: render::Surface,
+    instance: std::rc::Rc<render::Instance>,
+}
+
+impl Gpu {
+    fn new(
+        window: &window::Window,
+        device: Option<&render::DeviceSelector>,
+        textures: &[render::Image],
+        culling: render::Culling,
+        present_mode: render::PresentMode,
+    ) -> Result<Self, render::RenderError> {
+        let instance = render::Instance::new(window, device)?;
+        let surface = render::Surface::new(instance.clone(), window, present_mode)?;
+        let renderer = render::Renderer::new(&surface)?;
+        let mut voxel_renderer = render::VoxelRenderer::new(&surface)?;
+        voxel_renderer.set_textures(textures)?;
+        voxel_renderer.set_culling(culling);
+        let outline_renderer = render::OutlineRenderer::new(&surface)?;
+        Ok(Self {
+            voxel_renderer,
+            outline_renderer,
+            renderer,
+            surface,
+            instance,
+        })
+    }
+}
+
+// Parses the environment variable `name`, keeping `default` if it's unset or doesn't parse.
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T
+where
+    T::Err: std::fmt::Display,
+{
+    match std::env::var(name) {
+        Ok(value) => value.parse().unwrap_or_else(|e| {
+            log::warn!("Ignoring {}: {}", name, e);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+// Logs why the file at `path` couldn't be opened and exits.
+fn open_or_exit<T, E: std::fmt::Display>(path: &std::ffi::OsStr, result: Result<T, E>) -> T {
+    result.unwrap_or_else(|e| {
+        log::error!("Failed to open {:?}: {}", path, e);
+        std::process::exit(1)
+    })
+}
+
+// Logs a rendering error and exits.
+fn or_exit<T>(result: Result<T, render::RenderError>) -> T {
+    result.unwrap_or_else(|e| {
+        log::error!("{}", e);
+        std::process::exit(1)
+    })
+}
+
+fn remesh_dirty(
+    voxel_renderer: &mut render::VoxelRenderer,
+    object: &mut voxel::Object,
+    blocks: &voxel::BlockRegistry,
+    strategy: voxel::MeshStrategy,
+) -> Result<(), render::RenderError> {
+    object.update_light(blocks);
+    for coord in object.take_dirty() {
+        let faces = object.faces(coord, blocks, strategy).unwrap_or_default();
+        voxel_renderer.set_chunk(coord, &faces)?
+    }
+    Ok(())
+}
+
+// Renders a single frame without opening a window.
+fn screenshot(
+    object: &mut voxel::Object,
+    blocks: &voxel::BlockRegistry,
+    textures: &[render::Image],
+    strategy: voxel::MeshStrategy,
+    camera: &render::Camera,
+    sun: &render::Sun,
+    (width, height): (u32, u32),
+    device: Option<&render::DeviceSelector>,
+) -> Result<render::Image, render::RenderError> {
+    let instance = render::Instance::headless(device)?;
+    let offscreen = render::Offscreen::new(instance.clone(), width, height)?;
+    let mut renderer = render::Renderer::new(&offscreen)?;
+    let mut voxel_renderer = render::VoxelRenderer::new(&offscreen)?;
+    voxel_renderer.set_textures(textures)?;
+
+    remesh_dirty(&mut voxel_renderer, object, blocks, strategy)?;
+    let matrix = camera.view_matrix(VERTICAL_FOV, offscreen.aspect_ratio());
+    voxel_renderer.prepare(sun, &matrix)?;
+    let image = renderer.render_offscreen(
+        &offscreen,
+        |command_buffer| voxel_renderer.prepass(command_buffer),
+        |command_buffer| {
+            voxel_renderer.draw(command_buffer);
+        },
+    )?;
+
+    instance.wait_idle()?;
+    Ok(image)
+}
+
+struct Args {
+    scene: Option<std::ffi::OsString>,
+    screenshot: Option<std::path::PathBuf>,
+    golden: Option<std::path::PathBuf>,
+    bless: bool,
+    cpu: bool,
+    size: (u32, u32),
+    camera: render::Camera,
+    device: Option<render::DeviceSelector>,
+    list_devices: bool,
+}
+
+impl Args {
+    const USAGE: &'static str =
+        "usage: voxelite [--screenshot PATH] [--size WxH] [--camera X,Y,Z,YAW,PITCH] [--cpu] [--device INDEX|NAME] [SCENE]\n       voxelite --golden DIR [--bless] [--cpu] [--device INDEX|NAME]\n       voxelite --list-devices";
+
+    fn parse() -> Self {
+        let mut args = Self {
+            scene: None,
+            screenshot: None,
+            golden: None,
+            bless: false,
+            cpu: false,
+            size: (800, 600),
+            camera: render::Camera::new(ultraviolet::Vec3::new(0., 0., 16.), 0., 0.),
+            device: None,
+            list_devices: false,
+        };
+
+        let mut iter = std::env::args_os().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.to_str() {
+                Some("--screenshot") => args.screenshot = Some(Self::value(&mut iter).into()),
+                Some("--golden") => args.golden = Some(Self::value(&mut iter).into()),
+                Some("--bless") => args.bless = true,
+                Some("--cpu") => args.cpu = true,
+                Some("--device") => {
+                    args.device = Some(render::DeviceSelector::parse(&Self::string(&mut iter)))
+                }
+                Some("--list-devices") => args.list_devices = true,
+                Some("--size") => {
+                    args.size = Self::parse_size(&Self::string(&mut iter)).unwrap_or_else(|| {
+                        Self::fail("--size expects WIDTHxHEIGHT, e.g. 800x600")
+                    })
+                }
+                Some("--camera") => {
+                    args.camera = Self::parse_camera(&Self::string(&mut iter)).unwrap_or_else(|| {
+                        Self::fail("--camera expects X,Y,Z,YAW,PITCH with angles in degrees")
+                    })
+                }
+                Some(flag) if flag.starts_with("--") => {
+                    Self::fail(&format!("unknown option {}", flag))
+                }
+                _ if args.scene.is_none() => args.scene = Some(arg),
+                _ => Self::fail("only one scene can be opened"),
+            }
+        }
+
+        args
+    }
+
+    fn value(iter: &mut impl Iterator<Item = std::ffi::OsString>) -> std::ffi::OsString {
+        iter.next()
+            .unwrap_or_else(|| Self::fail("missing value for option"))
+    }
+
+    fn string(iter: &mut impl Iterator<Item = std::ffi::OsString>) -> String {
+        Self::value(iter)
+            .into_string()
+            .unwrap_or_else(|_| Self::fail("option value is not valid unicode"))
+    }
+
+    fn parse_size(value: &str) -> Option<(u32, u32)> {
+        let (width, height) = value.split_once('x')?;
+        let size = (width.parse().ok()?, height.parse().ok()?);
+        if size.0 == 0 || size.1 == 0 {
+            return None;
+        }
+        Some(size)
+    }
+
+    fn parse_camera(value: &str) -> Option<render::Camera> {
+        let values = value
+            .split(',')
+            .map(|value| value.trim().parse::<f32>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        match values[..] {
+            [x, y, z, yaw, pitch] => Some(render::Camera::new(
+                ultraviolet::Vec3::new(x, y, z),
+                yaw.to_radians(),
+                pitch.to_radians(),
+            )),
+            _ => None,
+        }
+    }
+
+    fn fail(message: &str) -> ! {
+        eprintln!("{}\n{}", message, Self::USAGE);
+        std::process::exit(2)
+    }
+}
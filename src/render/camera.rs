@@ -32,6 +32,16 @@ impl PlayerCamera {
     pub fn matrix(&self, vertical_fov: f32, aspect_ratio: f32) -> ultraviolet::Mat4 {
         self.camera.view_matrix(vertical_fov, aspect_ratio)
     }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+}
+
+impl From<Camera> for PlayerCamera {
+    fn from(camera: Camera) -> Self {
+        Self { camera }
+    }
 }
 
 pub struct Camera {
@@ -64,9 +74,22 @@ impl Camera {
         ultraviolet::Mat3::from_rotation_z(self.orientation.x)
     }
 
-    pub fn view_matrix(&self, vertical_fov: f32, aspect_ratio: f32) -> ultraviolet::Mat4 {
+    pub fn pos(&self) -> ultraviolet::Vec3 {
+        self.pos
+    }
+
+    pub fn look(&self) -> ultraviolet::Vec3 {
         let (sin, cos) = self.orientation.y.sin_cos();
-        let look_vec = self.forward() * cos + Self::UP * sin;
+        self.forward() * cos + Self::UP * sin
+    }
+
+    // The ray through the center of the view.
+    pub fn ray(&self) -> crate::voxel::Ray {
+        crate::voxel::Ray::new(self.pos, self.look())
+    }
+
+    pub fn view_matrix(&self, vertical_fov: f32, aspect_ratio: f32) -> ultraviolet::Mat4 {
+        let look_vec = self.look();
         let projection = ultraviolet::projection::perspective_infinite_z_vk(
             vertical_fov,
             aspect_ratio,
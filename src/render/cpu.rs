@@ -0,0 +1,204 @@
+// A software renderer that casts one ray per pixel through the scene. It shades faces the same way
+// as `voxel.vert` and projects with the same `Camera::view_matrix`, so its output can be compared
+// against the GPU's, and it works on machines without Vulkan. Textures are sampled from their
+// full-size level only, so distant textured faces won't match the GPU's mipmapped output exactly,
+// and shadows are traced exactly instead of looked up in a filtered shadow map, so their edges are
+// sharper.
+
+// Matches `shades` in voxel.vert, indexed by face direction (+x, -x, +y, -y, +z, -z).
+const SHADES: [f32; 6] = [0.8, 0.7, 0.85, 0.75, 1.0, 0.6];
+
+// Matches `normals` in voxel.vert.
+const NORMALS: [[f32; 3]; 6] = [
+    [1., 0., 0.],
+    [-1., 0., 0.],
+    [0., 1., 0.],
+    [0., -1., 0.],
+    [0., 0., 1.],
+    [0., 0., -1.],
+];
+
+// Matches `blockLightColor` in voxel.frag.
+const BLOCK_LIGHT_COLOR: [f32; 3] = [1.0, 0.85, 0.6];
+
+// How far shadow rays start out from the face they leave, so they don't hit it.
+const SHADOW_OFFSET: f32 = 0.01;
+
+// Matches `aoLevels` in voxel.vert.
+const AO_LEVELS: [f32; 4] = [0.45, 0.65, 0.82, 1.0];
+
+// Match `uAxes` and `vAxes` in voxel.vert.
+const U_AXES: [[f32; 3]; 6] = [
+    [0., 1., 0.],
+    [0., -1., 0.],
+    [-1., 0., 0.],
+    [1., 0., 0.],
+    [1., 0., 0.],
+    [1., 0., 0.],
+];
+const V_AXES: [[f32; 3]; 6] = [
+    [0., 0., -1.],
+    [0., 0., -1.],
+    [0., 0., -1.],
+    [0., 0., -1.],
+    [0., -1., 0.],
+    [0., 1., 0.],
+];
+
+// The object's light should be up to date, see `Object::update_light`.
+pub fn render(
+    object: &crate::voxel::Object,
+    registry: &crate::voxel::BlockRegistry,
+    textures: &[crate::render::Image], // as from `block_textures`
+    camera: &crate::render::Camera,
+    sun: &crate::render::Sun,
+    vertical_fov: f32,
+    width: u32,
+    height: u32,
+) -> crate::render::Image {
+    let inverse = camera
+        .view_matrix(vertical_fov, width as f32 / height as f32)
+        .inversed();
+    let unproject = |x: f32, y: f32, z: f32| {
+        let point = inverse * ultraviolet::Vec4::new(x, y, z, 1.);
+        point.xyz() / point.w
+    };
+
+    // Nothing can be hit past the farthest corner of the object.
+    let max_distance = object.bounds().map_or(0., |(min, max)| {
+        let corner = |i: usize| {
+            let distance = |v: i32| (v as f32 - camera.pos()[i]).abs() + 0.5;
+            distance(min[i]).max(distance(max[i]))
+        };
+        ultraviolet::Vec3::new(corner(0), corner(1), corner(2)).mag()
+    });
+    // Shadow rays start inside the object, so they can't go farther than its diagonal.
+    let shadow_distance = object.bounds().map_or(0., |(min, max)| {
+        let extent = |i: usize| (max[i] - min[i] + 1) as f32;
+        ultraviolet::Vec3::new(extent(0), extent(1), extent(2)).mag()
+    });
+
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    for py in 0..height {
+        for px in 0..width {
+            // Sample at the pixel center, in Vulkan's normalized device coordinates (y down).
+            let x = (px as f32 + 0.5) / width as f32 * 2. - 1.;
+            let y = (py as f32 + 0.5) / height as f32 * 2. - 1.;
+            let near = unproject(x, y, 0.);
+            let ray = crate::voxel::Ray::new(camera.pos(), unproject(x, y, 0.5) - near);
+
+            let color = match object.raycast_visible(&ray, max_distance, registry) {
+                Some((hit, block)) => {
+                    let direction = direction(hit.normal);
+                    let point = ray.at(hit.distance);
+                    let texel = textures
+                        .get(registry.layer(block, direction) as usize)
+                        .map_or([0xff; 4], |texture| sample(texture, point, direction));
+                    let lighting = object.face_lighting(hit.voxel, direction, registry);
+                    let ao = ambient_occlusion(lighting, point, direction);
+                    let normal = ultraviolet::Vec3::from(NORMALS[direction]);
+                    let diffuse = normal.dot(sun.direction()).max(0.);
+                    let lit = diffuse > 0. && {
+                        let shadow_ray =
+                            crate::voxel::Ray::new(point + normal * SHADOW_OFFSET, sun.direction());
+                        object
+                            .raycast_visible(&shadow_ray, shadow_distance, registry)
+                            .is_none()
+                    };
+                    let sky_light = light_level(lighting >> 20 & 0xf);
+                    let block_light = match lighting >> 16 & 0xf {
+                        0 => 0.,
+                        level => light_level(level),
+                    };
+                    let light = [0, 1, 2].map(|i| {
+                        (sun.ambient * SHADES[direction]
+                            + (1. - sun.ambient) * sun.color[i] * diffuse * lit as i32 as f32)
+                            * sky_light
+                            + BLOCK_LIGHT_COLOR[i] * block_light
+                    });
+                    shade(registry.get(block).color, texel, ao, light)
+                }
+                None => [0; 4],
+            };
+            pixels.extend_from_slice(&color)
+        }
+    }
+
+    crate::render::Image::from_rgba(width, height, pixels)
+}
+
+fn direction(normal: [i32; 3]) -> usize {
+    let axis = normal.iter().position(|&n| n != 0).unwrap_or(0);
+    axis * 2 + (normal[axis] < 0) as usize
+}
+
+// Nearest-neighbor lookup of the texel at `point` on a face, with the same UVs as voxel.vert.
+fn sample(texture: &crate::render::Image, point: ultraviolet::Vec3, direction: usize) -> [u8; 4] {
+    let corner = point + ultraviolet::Vec3::broadcast(0.5);
+    let coordinate = |axis: [f32; 3], size: u32| {
+        let t = corner.dot(ultraviolet::Vec3::from(axis)).rem_euclid(1.);
+        ((t * size as f32) as usize).min(size as usize - 1)
+    };
+    let x = coordinate(U_AXES[direction], texture.width());
+    let y = coordinate(V_AXES[direction], texture.height());
+    let index = (y * texture.width() as usize + x) * 4;
+    let mut texel = [0; 4];
+    texel.copy_from_slice(&texture.pixels()[index..index + 4]);
+    texel
+}
+
+// Matches `lightLevel` in voxel.vert.
+fn light_level(level: u32) -> f32 {
+    0.8f32.powi(15 - level as i32)
+}
+
+// Interpolates the corner ambient occlusion of a face across the triangle containing `point`,
+// like the GPU does for the quad voxel.vert emits.
+fn ambient_occlusion(lighting: u32, point: ultraviolet::Vec3, direction: usize) -> f32 {
+    let (_, u_axis, v_axis) = crate::voxel::DIRECTION_AXES[direction];
+    let s = (point[u_axis] + 0.5).rem_euclid(1.);
+    let t = (point[v_axis] + 0.5).rem_euclid(1.);
+    let [a00, a10, a01, a11] =
+        [0, 1, 2, 3].map(|corner| AO_LEVELS[(lighting >> (2 * corner) & 3) as usize]);
+
+    if lighting & crate::voxel::Face::FLIP == 0 {
+        if s + t <= 1. {
+            a00 + s * (a10 - a00) + t * (a01 - a00)
+        } else {
+            a11 + (1. - s) * (a01 - a11) + (1. - t) * (a10 - a11)
+        }
+    } else if t >= s {
+        a00 + t * (a01 - a00) + s * (a11 - a01)
+    } else {
+        a00 + s * (a10 - a00) + t * (a11 - a10)
+    }
+}
+
+// The color voxel.frag outputs for a face, written to an sRGB target.
+fn shade(color: [u8; 4], texel: [u8; 4], ao: f32, light: [f32; 3]) -> [u8; 4] {
+    let channel = |i: usize| {
+        let linear = (color[i] as f32 / 255.).powf(2.2)
+            * ao
+            * light[i]
+            * srgb_to_linear(texel[i] as f32 / 255.);
+        (linear_to_srgb(linear) * 255.).round() as u8
+    };
+    [channel(0), channel(1), channel(2), 255]
+}
+
+fn srgb_to_linear(srgb: f32) -> f32 {
+    if srgb <= 0.040_45 {
+        srgb / 12.92
+    } else {
+        ((srgb + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(linear: f32) -> f32 {
+    let linear = linear.clamp(0., 1.);
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1. / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,141 @@
+// The region of space a view-projection matrix maps into Vulkan's clip volume, as six planes
+// (left, right, bottom, top, near, far). A point `p` is inside a plane `(a, b, c, d)` when
+// `a * p.x + b * p.y + c * p.z + d >= 0`. Projections with an infinite far plane, like
+// `Camera::view_matrix`, get a far plane that every point is inside.
+#[derive(Copy, Clone, Debug)]
+pub struct Frustum {
+    planes: [ultraviolet::Vec4; 6],
+}
+
+impl Frustum {
+    // Gribb and Hartmann's method, for clip-space depth from 0 to w.
+    pub fn from_matrix(matrix: &ultraviolet::Mat4) -> Self {
+        let [x, y, z, w] = matrix.transposed().cols;
+        Self {
+            planes: [w + x, w - x, w + y, w - y, z, w - z],
+        }
+    }
+
+    // Whether any part of the axis-aligned box from `min` to `max` might be inside. Boxes near the
+    // frustum's corners can be reported as inside when they're not, but never the reverse.
+    pub fn intersects_box(&self, min: ultraviolet::Vec3, max: ultraviolet::Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner farthest along the plane's normal.
+            let corner = ultraviolet::Vec3::new(
+                if plane.x >= 0. { max.x } else { min.x },
+                if plane.y >= 0. { max.y } else { min.y },
+                if plane.z >= 0. { max.z } else { min.z },
+            );
+            plane.xyz().dot(corner) + plane.w >= 0.
+        })
+    }
+
+    // In the order given above, for testing on the GPU.
+    pub fn planes(&self) -> &[ultraviolet::Vec4; 6] {
+        &self.planes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Frustum;
+    use ultraviolet::{Mat4, Vec3, Vec4};
+
+    // Looks down +z from the origin, 90 degrees wide and high, from z = 1 to z = 10.
+    fn perspective() -> Frustum {
+        Frustum::from_matrix(&Mat4::new(
+            Vec4::new(1., 0., 0., 0.),
+            Vec4::new(0., 1., 0., 0.),
+            Vec4::new(0., 0., 10. / 9., 1.),
+            Vec4::new(0., 0., -10. / 9., 0.),
+        ))
+    }
+
+    // The box from (-2, -1, 1) to (2, 1, 10).
+    fn orthographic() -> Frustum {
+        Frustum::from_matrix(&Mat4::new(
+            Vec4::new(0.5, 0., 0., 0.),
+            Vec4::new(0., 1., 0., 0.),
+            Vec4::new(0., 0., 1. / 9., 0.),
+            Vec4::new(0., 0., -1. / 9., 1.),
+        ))
+    }
+
+    fn contains(frustum: &Frustum, point: Vec3) -> bool {
+        frustum.intersects_box(point, point)
+    }
+
+    // Checks a point just inside and one just outside of each plane, in the order of `planes`.
+    fn assert_planes(frustum: &Frustum, points: [(Vec3, Vec3); 6]) {
+        for (plane, &(inside, outside)) in points.iter().enumerate() {
+            assert!(contains(frustum, inside), "plane {}: {:?}", plane, inside);
+            assert!(
+                !contains(frustum, outside),
+                "plane {}: {:?}",
+                plane,
+                outside
+            );
+        }
+    }
+
+    #[test]
+    fn perspective_planes() {
+        assert_planes(
+            &perspective(),
+            [
+                (Vec3::new(-4.99, 0., 5.), Vec3::new(-5.01, 0., 5.)),
+                (Vec3::new(4.99, 0., 5.), Vec3::new(5.01, 0., 5.)),
+                (Vec3::new(0., -4.99, 5.), Vec3::new(0., -5.01, 5.)),
+                (Vec3::new(0., 4.99, 5.), Vec3::new(0., 5.01, 5.)),
+                (Vec3::new(0., 0., 1.01), Vec3::new(0., 0., 0.99)),
+                (Vec3::new(0., 0., 9.99), Vec3::new(0., 0., 10.01)),
+            ],
+        );
+    }
+
+    #[test]
+    fn orthographic_planes() {
+        assert_planes(
+            &orthographic(),
+            [
+                (Vec3::new(-1.99, 0., 5.), Vec3::new(-2.01, 0., 5.)),
+                (Vec3::new(1.99, 0., 5.), Vec3::new(2.01, 0., 5.)),
+                (Vec3::new(0., -0.99, 5.), Vec3::new(0., -1.01, 5.)),
+                (Vec3::new(0., 0.99, 5.), Vec3::new(0., 1.01, 5.)),
+                (Vec3::new(0., 0., 1.01), Vec3::new(0., 0., 0.99)),
+                (Vec3::new(0., 0., 9.99), Vec3::new(0., 0., 10.01)),
+            ],
+        );
+    }
+
+    #[test]
+    fn behind_the_camera() {
+        let frustum = perspective();
+        assert!(!contains(&frustum, Vec3::new(0., 0., -5.)));
+        // Behind the camera, the other side planes would let this one through.
+        assert!(!contains(&frustum, Vec3::new(-2., 2., -5.)));
+        assert!(!contains(&frustum, Vec3::zero()));
+
+        let camera = crate::render::Camera::new(Vec3::new(3., -2., 7.), 0.8, -0.3);
+        let frustum = Frustum::from_matrix(&camera.view_matrix(45., 4. / 3.));
+        assert!(!contains(&frustum, camera.pos() - camera.look()));
+        assert!(contains(&frustum, camera.pos() + camera.look()));
+        // The far plane is infinitely far away.
+        assert!(contains(&frustum, camera.pos() + camera.look() * 1e6));
+    }
+
+    #[test]
+    fn boxes() {
+        let frustum = perspective();
+        let intersects =
+            |min: [f32; 3], max: [f32; 3]| frustum.intersects_box(Vec3::from(min), Vec3::from(max));
+        // Straddling the right plane, then the near plane.
+        assert!(intersects([4., -1., 4.], [6., 1., 6.]));
+        assert!(intersects([-1., -1., 0.], [1., 1., 2.]));
+        // Around the whole frustum.
+        assert!(intersects([-20., -20., -20.], [20., 20., 20.]));
+        // Just outside the right plane, and behind the camera.
+        assert!(!intersects([5.1, -1., 4.], [6., 1., 5.]));
+        assert!(!intersects([-1., -1., -3.], [1., 1., -2.]));
+    }
+}
@@ -0,0 +1,424 @@
+// An RGBA8 image read back from the GPU, stored row by row from the top left.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        assert_eq!(pixels.len(), width as usize * height as usize * 4);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    // Binary PPM (P6). Alpha is dropped.
+    pub fn write_ppm<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        let rgb = self
+            .pixels
+            .chunks_exact(4)
+            .flat_map(|pixel| pixel[..3].iter().copied())
+            .collect::<Vec<_>>();
+        writer.write_all(&rgb)
+    }
+
+    // Reads a binary PPM with a maximum value of 255, as written by `write_ppm`.
+    pub fn read_ppm<R: std::io::Read>(mut reader: R) -> std::io::Result<Self> {
+        let invalid = |message: &str| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+        };
+
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        // The header is four whitespace-separated fields, with `#` comments running to the end of
+        // the line, followed by a single whitespace byte.
+        let mut fields = Vec::with_capacity(4);
+        let mut index = 0;
+        while fields.len() < 4 {
+            match bytes.get(index) {
+                None => return Err(invalid("PPM header is truncated")),
+                Some(b'#') => {
+                    while bytes.get(index).is_some_and(|&byte| byte != b'\n') {
+                        index += 1
+                    }
+                }
+                Some(byte) if byte.is_ascii_whitespace() => index += 1,
+                Some(_) => {
+                    let start = index;
+                    while bytes
+                        .get(index)
+                        .is_some_and(|byte| !byte.is_ascii_whitespace())
+                    {
+                        index += 1
+                    }
+                    fields.push(String::from_utf8_lossy(&bytes[start..index]).into_owned())
+                }
+            }
+        }
+        index += 1;
+
+        if fields[0] != "P6" {
+            return Err(invalid("not a binary PPM"));
+        }
+        let parse = |field: &str| {
+            field
+                .parse::<u32>()
+                .map_err(|_| invalid("invalid PPM header"))
+        };
+        let (width, height) = (parse(&fields[1])?, parse(&fields[2])?);
+        if parse(&fields[3])? != 255 {
+            return Err(invalid("only 8-bit PPMs are supported"));
+        }
+
+        let rgb = (width as usize)
+            .checked_mul(height as usize * 3)
+            .and_then(|size| bytes.get(index..index.checked_add(size)?))
+            .ok_or_else(|| invalid("PPM pixel data is truncated"))?;
+        let pixels = rgb
+            .chunks_exact(3)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+            .collect();
+        Ok(Self::from_rgba(width, height, pixels))
+    }
+
+    pub fn load_ppm<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Self> {
+        Self::read_ppm(std::io::BufReader::new(std::fs::File::open(path)?))
+    }
+
+    // Compares the color channels of two images of the same size. Pixels where any channel differs
+    // by more than `tolerance` are counted as different, and shown white in the returned diff image.
+    pub fn diff(&self, other: &Self, tolerance: u8) -> Option<ImageDiff> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return None;
+        }
+
+        let mut differing = 0;
+        let mut max_delta = 0;
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for (a, b) in self.pixels.chunks_exact(4).zip(other.pixels.chunks_exact(4)) {
+            let delta = (0..3).map(|i| a[i].abs_diff(b[i])).max().unwrap();
+            max_delta = max_delta.max(delta);
+            let value = if delta > tolerance {
+                differing += 1;
+                255
+            } else {
+                a[0] / 4
+            };
+            pixels.extend_from_slice(&[value, value, value, 255])
+        }
+
+        Some(ImageDiff {
+            differing,
+            max_delta,
+            image: Self::from_rgba(self.width, self.height, pixels),
+        })
+    }
+
+    // 8-bit RGBA PNG. The image data is stored uncompressed to avoid pulling in a deflate
+    // implementation, so files are about as large as the raw pixels.
+    pub fn write_png<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        header.extend_from_slice(&[8, 6, 0, 0, 0]); // depth, RGBA, deflate, no filter, no interlace
+
+        let stride = self.width as usize * 4;
+        let mut raw = Vec::with_capacity((stride + 1) * self.height as usize);
+        for row in self.pixels.chunks_exact(stride.max(1)) {
+            raw.push(0); // filter type: none
+            raw.extend_from_slice(row)
+        }
+
+        writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+        write_png_chunk(&mut writer, b"IHDR", &header)?;
+        write_png_chunk(&mut writer, b"IDAT", &zlib_stored(&raw))?;
+        write_png_chunk(&mut writer, b"IEND", &[])
+    }
+
+    // Picks the format from the extension: `.ppm`, or PNG for anything else.
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("ppm") => {
+                self.write_ppm(&mut writer)?
+            }
+            _ => self.write_png(&mut writer)?,
+        }
+        std::io::Write::flush(&mut writer)
+    }
+}
+
+// Loads the layers of the block texture array: plain white, then every texture the registry uses.
+// Textures that can't be loaded, or whose size differs from the first one, are logged and left
+// white.
+pub fn block_textures(registry: &crate::voxel::BlockRegistry) -> Vec<Image> {
+    let mut size = None;
+    let loaded = registry
+        .textures()
+        .iter()
+        .map(|name| {
+            let path = registry.texture_path(name);
+            match Image::load_ppm(&path) {
+                Ok(image) if *size.get_or_insert(image.size()) == image.size() => Some(image),
+                Ok(image) => {
+                    log::warn!(
+                        "Texture {:?} is {}x{}, but textures must all be the same size",
+                        path,
+                        image.width,
+                        image.height
+                    );
+                    None
+                }
+                Err(e) => {
+                    log::warn!("Failed to load texture {:?}: {}", path, e);
+                    None
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let (width, height) = size.unwrap_or((1, 1));
+    let white = || {
+        Image::from_rgba(width, height, vec![0xff; width as usize * height as usize * 4])
+    };
+    std::iter::once(white())
+        .chain(loaded.into_iter().map(|image| image.unwrap_or_else(white)))
+        .collect()
+}
+
+pub struct ImageDiff {
+    pub differing: usize,
+    pub max_delta: u8,
+    pub image: Image,
+}
+
+impl ImageDiff {
+    // The fraction of pixels that differ by more than the tolerance.
+    pub fn ratio(&self) -> f64 {
+        self.differing as f64 / (self.image.width as f64 * self.image.height as f64).max(1.)
+    }
+}
+
+fn write_png_chunk<W: std::io::Write>(
+    writer: &mut W,
+    kind: &[u8; 4],
+    data: &[u8],
+) -> std::io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let crc = crc32(crc32(!0, kind), data);
+    writer.write_all(&(!crc).to_be_bytes())
+}
+
+// Wraps `data` in a zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = 0xffff;
+
+    let mut out = Vec::with_capacity(data.len() + data.len() / MAX_BLOCK * 5 + 11);
+    out.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let length = block.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&length.to_le_bytes());
+        out.extend_from_slice(&(!length).to_le_bytes());
+        out.extend_from_slice(block)
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            }
+        }
+    }
+    crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 3x2 image with every channel different.
+    fn image() -> Image {
+        let pixels = (0..6u8)
+            .flat_map(|i| [i * 40, i * 40 + 1, 255 - i, 255])
+            .collect();
+        Image::from_rgba(3, 2, pixels)
+    }
+
+    fn read(bytes: &[u8]) -> std::io::Result<Image> {
+        Image::read_ppm(bytes)
+    }
+
+    #[test]
+    fn ppm_round_trip() {
+        let mut bytes = Vec::new();
+        image().write_ppm(&mut bytes).unwrap();
+        assert!(bytes.starts_with(b"P6\n3 2\n255\n"));
+        assert_eq!(read(&bytes).unwrap(), image());
+    }
+
+    #[test]
+    fn ppm_header_comments_and_whitespace() {
+        let pixels = [1, 2, 3, 4, 5, 6];
+        for header in [
+            &b"P6 2 1 255\n"[..],
+            b"P6\t2\r\n1   255 ",
+            b"# made by hand\nP6\n2 1\n255\n",
+            b"P6 # size follows\n2 # width\n#\n1\n255\n",
+        ] {
+            let image = read(&[header, &pixels].concat()).unwrap();
+            assert_eq!(image.size(), (2, 1));
+            assert_eq!(image.pixels(), [1, 2, 3, 255, 4, 5, 6, 255]);
+        }
+        // Only a single whitespace byte separates the header from the pixels.
+        let image = read(b"P6 2 1 255\n\n\n\n\n\n\n").unwrap();
+        assert_eq!(
+            image.pixels(),
+            [b'\n', b'\n', b'\n', 255, b'\n', b'\n', b'\n', 255]
+        );
+    }
+
+    #[test]
+    fn invalid_ppm() {
+        for bytes in [
+            &b""[..],
+            b"P6 2 1",
+            b"P6 2 1 # 255\n",
+            b"P3 2 1 255\n123456",
+            b"P6 2 x 255\n123456",
+            b"P6 2 1 65535\n123456123456",
+            b"P6 2 1 255\n12345",
+        ] {
+            let e = read(bytes).unwrap_err();
+            assert_eq!(e.kind(), std::io::ErrorKind::InvalidData, "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn diff() {
+        let a = Image::from_rgba(4, 1, [[100, 50, 50, 255]; 4].concat());
+        let b = Image::from_rgba(
+            4,
+            1,
+            [
+                [100, 50, 50, 0],
+                [92, 58, 50, 255],
+                [100, 50, 59, 255],
+                [0, 250, 50, 255],
+            ]
+            .concat(),
+        );
+
+        let diff = a.diff(&b, 8).unwrap();
+        assert_eq!(diff.differing, 2);
+        assert_eq!(diff.max_delta, 200);
+        assert_eq!(diff.ratio(), 0.5);
+        assert_eq!(
+            diff.image.pixels(),
+            [[25, 25, 25, 255], [25, 25, 25, 255], [255; 4], [255; 4]].concat()
+        );
+
+        let diff = a.diff(&b, 200).unwrap();
+        assert_eq!((diff.differing, diff.max_delta), (0, 200));
+        assert!(a.diff(&image(), 8).is_none());
+    }
+
+    #[test]
+    fn png() {
+        let mut bytes = Vec::new();
+        image().write_png(&mut bytes).unwrap();
+
+        assert!(bytes.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x03\0\0\0\x02\x08\x06"));
+        assert!(bytes.ends_with(b"\0\0\0\0IEND\xae\x42\x60\x82"));
+        // A single stored deflate block holding each row after a filter byte.
+        let idat = &bytes[33..bytes.len() - 12];
+        let raw = [
+            &[0][..],
+            &image().pixels()[..12],
+            &[0],
+            &image().pixels()[12..],
+        ]
+        .concat();
+        assert_eq!(&idat[..4], (idat.len() as u32 - 12).to_be_bytes());
+        assert_eq!(&idat[4..8], b"IDAT");
+        assert_eq!(&idat[8..15], [0x78, 0x01, 1, 26, 0, !26, 0xff]);
+        assert_eq!(&idat[15..41], raw);
+        assert_eq!(&idat[41..45], adler32(&raw).to_be_bytes());
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(!crc32(!0, b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn save_picks_the_format() {
+        let dir = std::env::temp_dir().join(format!("voxelite-image-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, magic) in [
+            ("a.ppm", &b"P6"[..]),
+            ("a.PPM", b"P6"),
+            ("a.png", b"\x89PNG"),
+        ] {
+            image().save(dir.join(name)).unwrap();
+            assert!(
+                std::fs::read(dir.join(name)).unwrap().starts_with(magic),
+                "{}",
+                name
+            );
+        }
+        assert_eq!(Image::load_ppm(dir.join("a.ppm")).unwrap(), image());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
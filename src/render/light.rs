@@ -0,0 +1,133 @@
+// A directional light infinitely far away, shining on the whole scene.
+#[derive(Copy, Clone, Debug)]
+pub struct Sun {
+    pub azimuth: f32,   // radians, counterclockwise from +x
+    pub elevation: f32, // radians above the horizon
+    pub color: [f32; 3],
+    pub ambient: f32, // fraction of light that reaches faces regardless of direction and shadow
+}
+
+impl Sun {
+    pub const PRESETS: [(&'static str, [f32; 3]); 3] = [
+        ("daylight", [1.0, 0.97, 0.9]),
+        ("sunset", [1.0, 0.6, 0.35]),
+        ("moonlight", [0.35, 0.4, 0.6]),
+    ];
+
+    const TURN_KEYS: [winit::event::VirtualKeyCode; 4] = [
+        winit::event::VirtualKeyCode::LBracket,
+        winit::event::VirtualKeyCode::RBracket,
+        winit::event::VirtualKeyCode::Minus,
+        winit::event::VirtualKeyCode::Equals,
+    ];
+    const TURN_SPEED: f32 = 1.; // radians per second
+
+    // Parses `azimuth,elevation` in degrees, optionally followed by `,rrggbb`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.split(',').map(str::trim);
+        let azimuth = parts.next()?.parse::<f32>().ok()?;
+        let elevation = parts.next()?.parse::<f32>().ok()?;
+        let color = match parts.next() {
+            Some(hex) => {
+                let hex = hex.strip_prefix('#').unwrap_or(hex);
+                if hex.len() != 6 || !hex.is_ascii() {
+                    return None;
+                }
+                let channel = |i: usize| {
+                    u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+                        .ok()
+                        .map(|c| c as f32 / 255.)
+                };
+                [channel(0)?, channel(1)?, channel(2)?]
+            }
+            None => Self::default().color,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            azimuth: azimuth.to_radians(),
+            elevation: elevation.to_radians(),
+            color,
+            ..Self::default()
+        })
+    }
+
+    // `[` and `]` turn the sun around the vertical axis, `-` and `=` lower and raise it, and `L`
+    // cycles through the color presets.
+    pub fn update(&mut self, state: &crate::window::State) {
+        let step = Self::TURN_SPEED * state.frame_elapsed().as_secs_f32();
+        let [left, right, down, up] = Self::TURN_KEYS.map(|key| state.key_held(key) as i32 as f32);
+        self.azimuth = (self.azimuth + (right - left) * step).rem_euclid(std::f32::consts::TAU);
+        self.elevation = (self.elevation + (up - down) * step)
+            .clamp(-std::f32::consts::FRAC_PI_2, std::f32::consts::FRAC_PI_2);
+
+        if state.key_pressed(winit::event::VirtualKeyCode::L) {
+            let next = Self::PRESETS
+                .iter()
+                .position(|&(_, color)| color == self.color)
+                .map_or(0, |index| (index + 1) % Self::PRESETS.len());
+            let (name, color) = Self::PRESETS[next];
+            self.color = color;
+            log::info!("Sun color: {}", name)
+        }
+    }
+
+    // The unit vector pointing towards the sun.
+    pub fn direction(&self) -> ultraviolet::Vec3 {
+        let (sin_azimuth, cos_azimuth) = self.azimuth.sin_cos();
+        let (sin_elevation, cos_elevation) = self.elevation.sin_cos();
+        ultraviolet::Vec3::new(
+            cos_azimuth * cos_elevation,
+            sin_azimuth * cos_elevation,
+            sin_elevation,
+        )
+    }
+
+    // An orthographic view-projection from the sun that covers the box from `min` to `max`, for
+    // rendering a shadow map.
+    pub fn matrix(&self, min: ultraviolet::Vec3, max: ultraviolet::Vec3) -> ultraviolet::Mat4 {
+        let center = (min + max) * 0.5;
+        let radius = ((max - min).mag() * 0.5).max(1.);
+        let direction = self.direction();
+        let up = if direction.z.abs() > 0.99 {
+            ultraviolet::Vec3::unit_x()
+        } else {
+            ultraviolet::Vec3::unit_z()
+        };
+        let view = ultraviolet::Mat4::look_at(center + direction * radius, center, up);
+        let projection = ultraviolet::projection::orthographic_vk(
+            -radius,
+            radius,
+            -radius,
+            radius,
+            0.,
+            2. * radius,
+        );
+        projection * view
+    }
+}
+
+impl Default for Sun {
+    fn default() -> Self {
+        Self {
+            azimuth: 1.1,
+            elevation: 0.9,
+            color: Self::PRESETS[0].1,
+            ambient: 0.45,
+        }
+    }
+}
+
+impl std::str::FromStr for Sun {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s).ok_or_else(|| {
+            format!(
+                "expected AZIMUTH,ELEVATION[,RRGGBB] with angles in degrees, got `{}`",
+                s
+            )
+        })
+    }
+}
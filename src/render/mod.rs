@@ -1,5 +1,15 @@
 mod camera;
+pub mod cpu;
+mod frustum;
+mod image;
+mod light;
 mod vk;
 
 pub use camera::{Camera, PlayerCamera};
-pub use vk::{Instance, Renderer, Surface, VoxelRenderer};
+pub use frustum::Frustum;
+pub use image::{block_textures, Image};
+pub use light::Sun;
+pub use vk::{
+    Adapter, ChunkCounts, Culling, DeviceSelector, Instance, Offscreen, OutlineRenderer,
+    PresentMode, RenderError, Renderer, Surface, VoxelRenderer,
+};
@@ -1,12 +1,35 @@
+mod adapter;
+mod allocator;
+mod cull;
+mod error;
+mod face_pool;
+mod host_buffer;
 mod instance;
+mod offscreen;
+mod outline_renderer;
+mod owned;
 mod renderer;
+mod shadow;
 mod surface;
+mod texture;
 mod voxel_renderer;
 
+pub use adapter::{Adapter, DeviceSelector};
+use allocator::{Allocator, Buffer, FreeList, Image};
+pub use allocator::MemoryStats;
+use cull::CullPipeline;
+pub use error::RenderError;
+use face_pool::{FacePool, FaceRange};
+use host_buffer::HostBuffer;
 pub use instance::Instance;
+pub use offscreen::Offscreen;
+pub use outline_renderer::OutlineRenderer;
+use owned::Owned;
 pub use renderer::Renderer;
-pub use surface::Surface;
-pub use voxel_renderer::VoxelRenderer;
+use shadow::ShadowMap;
+pub use surface::{PresentMode, Surface};
+use texture::TextureArray;
+pub use voxel_renderer::{ChunkCounts, Culling, VoxelRenderer};
 
 mod debug {
     use erupt::vk;
@@ -59,8 +82,28 @@ struct SyncObject {
     command_buffer: vk::CommandBuffer,
 }
 
+// Something the renderers can draw into.
+pub trait Target {
+    fn instance(&self) -> std::rc::Rc<Instance>;
+    fn render_info(&self) -> RenderInfo;
+}
+
 #[derive(Copy, Clone)]
-struct RenderInfo {
+pub struct RenderInfo {
     render_pass: vk::RenderPass,
     extent: vk::Extent2D,
 }
+
+const CLEAR_VALUES: [vk::ClearValue; 2] = [
+    vk::ClearValue {
+        color: vk::ClearColorValue {
+            float32: [0.0, 0.0, 0.0, 0.0],
+        },
+    },
+    vk::ClearValue {
+        depth_stencil: vk::ClearDepthStencilValue {
+            depth: 1.0,
+            stencil: 0,
+        },
+    },
+];
@@ -0,0 +1,214 @@
+use erupt::vk;
+
+// A GPU as the driver describes it, for listing devices and choosing between them.
+pub struct Adapter {
+    pub index: usize,
+    pub name: String,
+    physical_device: vk::PhysicalDevice,
+    properties: vk::PhysicalDeviceProperties,
+    features: vk::PhysicalDeviceFeatures,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+}
+
+impl Adapter {
+    // Optional features worth knowing about when comparing GPUs, by their Vulkan names.
+    const FEATURES: &'static [(&'static str, fn(&vk::PhysicalDeviceFeatures) -> vk::Bool32)] = &[
+        ("multiDrawIndirect", |features| features.multi_draw_indirect),
+        ("drawIndirectFirstInstance", |features| {
+            features.draw_indirect_first_instance
+        }),
+        ("samplerAnisotropy", |features| features.sampler_anisotropy),
+        ("fillModeNonSolid", |features| features.fill_mode_non_solid),
+        ("wideLines", |features| features.wide_lines),
+        ("depthClamp", |features| features.depth_clamp),
+        ("geometryShader", |features| features.geometry_shader),
+        ("shaderInt64", |features| features.shader_int64),
+        ("textureCompressionBC", |features| {
+            features.texture_compression_bc
+        }),
+    ];
+
+    // Every GPU the driver exposes, in the order `--device` indexes them. Uses a throwaway
+    // instance, so it can be called before any renderer exists.
+    pub fn list() -> Result<Vec<Self>, super::RenderError> {
+        let entry = erupt::EntryLoader::new()?;
+        let instance_create_info = vk::InstanceCreateInfoBuilder::new();
+        let instance = unsafe { erupt::InstanceLoader::new(&entry, &instance_create_info, None) }?;
+        let adapters = Self::enumerate(&instance);
+        unsafe { instance.destroy_instance(None) }
+        adapters
+    }
+
+    pub(super) fn enumerate(
+        instance: &erupt::InstanceLoader,
+    ) -> Result<Vec<Self>, super::RenderError> {
+        let physical_devices = unsafe { instance.enumerate_physical_devices(None) }.result()?;
+        Ok(physical_devices
+            .into_iter()
+            .enumerate()
+            .map(|(index, physical_device)| unsafe {
+                let properties = instance.get_physical_device_properties(physical_device);
+                Self {
+                    index,
+                    name: std::ffi::CStr::from_ptr(properties.device_name.as_ptr())
+                        .to_string_lossy()
+                        .into_owned(),
+                    physical_device,
+                    properties,
+                    features: instance.get_physical_device_features(physical_device),
+                    memory_properties: instance
+                        .get_physical_device_memory_properties(physical_device),
+                }
+            })
+            .collect())
+    }
+
+    pub(super) fn physical_device(&self) -> vk::PhysicalDevice {
+        self.physical_device
+    }
+    pub(super) fn features(&self) -> &vk::PhysicalDeviceFeatures {
+        &self.features
+    }
+    pub(super) fn memory_properties(&self) -> vk::PhysicalDeviceMemoryProperties {
+        self.memory_properties
+    }
+
+    pub fn device_type(&self) -> vk::PhysicalDeviceType {
+        self.properties.device_type
+    }
+
+    // Drivers are free to pack their version however they like. NVIDIA and Intel's Windows
+    // driver have their own layouts, everyone else uses the Vulkan one.
+    pub fn driver_version(&self) -> String {
+        let version = self.properties.driver_version;
+        match self.properties.vendor_id {
+            0x10de => format!(
+                "{}.{}.{}.{}",
+                version >> 22,
+                (version >> 14) & 0xff,
+                (version >> 6) & 0xff,
+                version & 0x3f
+            ),
+            0x8086 if cfg!(windows) => format!("{}.{}", version >> 14, version & 0x3fff),
+            _ => format!(
+                "{}.{}.{}",
+                vk::api_version_major(version),
+                vk::api_version_minor(version),
+                vk::api_version_patch(version)
+            ),
+        }
+    }
+
+    // The capabilities this renderer cares about, as name and value pairs.
+    pub fn capabilities(&self) -> Vec<(&'static str, String)> {
+        let limits = &self.properties.limits;
+        let api_version = self.properties.api_version;
+        let device_local_memory: u64 = self.memory_properties.memory_heaps
+            [..self.memory_properties.memory_heap_count as usize]
+            .iter()
+            .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+            .map(|heap| heap.size)
+            .sum();
+        let features = Self::FEATURES
+            .iter()
+            .filter(|(_, supported)| supported(&self.features) != vk::FALSE)
+            .map(|&(name, _)| name)
+            .collect::<Vec<_>>();
+
+        vec![
+            ("type", format!("{:?}", self.device_type())),
+            (
+                "vendor",
+                format!(
+                    "{:#06x}, device {:#06x}",
+                    self.properties.vendor_id, self.properties.device_id
+                ),
+            ),
+            ("driver", self.driver_version()),
+            (
+                "vulkan",
+                format!(
+                    "{}.{}.{}",
+                    vk::api_version_major(api_version),
+                    vk::api_version_minor(api_version),
+                    vk::api_version_patch(api_version)
+                ),
+            ),
+            (
+                "device-local memory",
+                format!("{} MiB", device_local_memory >> 20),
+            ),
+            (
+                "max image size",
+                format!(
+                    "{}, {} layers",
+                    limits.max_image_dimension2_d, limits.max_image_array_layers
+                ),
+            ),
+            (
+                "max allocations",
+                limits.max_memory_allocation_count.to_string(),
+            ),
+            (
+                "max draw indirect count",
+                limits.max_draw_indirect_count.to_string(),
+            ),
+            (
+                "max compute invocations",
+                limits.max_compute_work_group_invocations.to_string(),
+            ),
+            (
+                "max push constants",
+                format!("{} bytes", limits.max_push_constants_size),
+            ),
+            ("max anisotropy", limits.max_sampler_anisotropy.to_string()),
+            ("features", features.join(", ")),
+        ]
+    }
+
+    // Logs which GPU was picked and what it can do.
+    pub fn log_report(&self) {
+        log::info!("Using GPU {}", self);
+        for (name, value) in self.capabilities() {
+            log::info!("  {}: {}", name, value)
+        }
+    }
+}
+
+impl std::fmt::Display for Adapter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.index, self.name)
+    }
+}
+
+// Which GPU to render with, either by its index in `Adapter::list` or by part of its name.
+#[derive(Clone, Debug)]
+pub enum DeviceSelector {
+    Index(usize),
+    Name(String),
+}
+
+impl DeviceSelector {
+    pub fn parse(value: &str) -> Self {
+        match value.trim().parse() {
+            Ok(index) => Self::Index(index),
+            Err(_) => Self::Name(value.trim().to_lowercase()),
+        }
+    }
+
+    pub(super) fn matches(&self, adapter: &Adapter) -> bool {
+        match self {
+            Self::Index(index) => adapter.index == *index,
+            Self::Name(name) => adapter.name.to_lowercase().contains(name.as_str()),
+        }
+    }
+}
+
+impl std::fmt::Display for DeviceSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "#{}", index),
+            Self::Name(name) => write!(f, "\"{}\"", name),
+        }
+    }
+}
@@ -0,0 +1,586 @@
+use erupt::vk;
+
+// Hands out device memory from large blocks, so buffers and images share a few `vkDeviceMemory`
+// allocations instead of each needing its own. Blocks are kept per memory type, and buffers and
+// images get separate blocks so `bufferImageGranularity` never comes into play. Allocations too
+// big to share a block get a dedicated one, which is freed with them. Host-visible blocks stay
+// mapped for their whole lifetime.
+pub struct Allocator {
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    blocks: std::cell::RefCell<Vec<Option<Block>>>, // freed blocks leave a `None` to reuse
+}
+
+struct Block {
+    memory: vk::DeviceMemory,
+    memory_type: u32,
+    kind: Kind,
+    size: u64,
+    mapped: *mut u8, // null unless host-visible
+    free: FreeList,
+    allocations: usize,
+    dedicated: bool,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    Buffer,
+    Image,
+}
+
+// A piece of a block, returned by `Allocator::allocate` and given back to `Allocator::free`.
+struct Allocation {
+    memory: vk::DeviceMemory,
+    block: usize,
+    offset: u64,
+    size: u64,
+    mapped: *mut u8,
+}
+
+impl Allocator {
+    const BLOCK_SIZE: u64 = 64 << 20;
+
+    pub(super) fn new(memory_properties: vk::PhysicalDeviceMemoryProperties) -> Self {
+        Self {
+            memory_properties,
+            blocks: std::cell::RefCell::new(Vec::new()),
+        }
+    }
+
+    fn memory_type_index(
+        &self,
+        properties: vk::MemoryPropertyFlags,
+        requirements: &vk::MemoryRequirements,
+    ) -> Result<u32, super::RenderError> {
+        (0..self.memory_properties.memory_type_count)
+            .find(|i| {
+                (((requirements.memory_type_bits >> i) & 1) != 0)
+                    && self.memory_properties.memory_types[*i as usize]
+                        .property_flags
+                        .contains(properties)
+            })
+            .ok_or(super::RenderError::NoSuitableMemoryType(properties))
+    }
+
+    fn allocate(
+        &self,
+        device: &erupt::DeviceLoader,
+        kind: Kind,
+        properties: vk::MemoryPropertyFlags,
+        requirements: vk::MemoryRequirements,
+    ) -> Result<Allocation, super::RenderError> {
+        let memory_type = self.memory_type_index(properties, &requirements)?;
+        let mut blocks = self.blocks.borrow_mut();
+
+        // First fit in an existing block.
+        for (index, block) in blocks.iter_mut().enumerate() {
+            let block = match block {
+                Some(block)
+                    if block.memory_type == memory_type
+                        && block.kind == kind
+                        && !block.dedicated =>
+                {
+                    block
+                }
+                _ => continue,
+            };
+            if let Some(offset) = block
+                .free
+                .allocate(requirements.size, requirements.alignment)
+            {
+                block.allocations += 1;
+                return Ok(block.allocation(index, offset, requirements.size));
+            }
+        }
+
+        let dedicated = requirements.size > Self::BLOCK_SIZE / 2;
+        let size = if dedicated {
+            requirements.size
+        } else {
+            Self::BLOCK_SIZE
+        };
+        let memory_allocate_info = vk::MemoryAllocateInfoBuilder::new()
+            .allocation_size(size)
+            .memory_type_index(memory_type);
+        let memory = unsafe { device.allocate_memory(&memory_allocate_info, None) }.result()?;
+        let mut mapped: *mut std::ffi::c_void = std::ptr::null_mut::<std::ffi::c_void>();
+        if self.memory_properties.memory_types[memory_type as usize]
+            .property_flags
+            .contains(vk::MemoryPropertyFlags::HOST_VISIBLE)
+        {
+            if let Err(e) =
+                unsafe { device.map_memory(memory, 0, vk::WHOLE_SIZE, None, &mut mapped) }.result()
+            {
+                unsafe { device.free_memory(Some(memory), None) }
+                return Err(e.into());
+            }
+        }
+
+        let mut block = Block {
+            memory,
+            memory_type,
+            kind,
+            size,
+            mapped: mapped.cast(),
+            free: FreeList::new(size),
+            allocations: 1,
+            dedicated,
+        };
+        let offset = block
+            .free
+            .allocate(requirements.size, requirements.alignment)
+            .unwrap();
+        let index = match blocks.iter().position(Option::is_none) {
+            Some(index) => index,
+            None => {
+                blocks.push(None);
+                blocks.len() - 1
+            }
+        };
+        let allocation = block.allocation(index, offset, requirements.size);
+        blocks[index] = Some(block);
+        Ok(allocation)
+    }
+
+    // Shared blocks are kept once empty, to be reused by the next allocations.
+    fn free(&self, device: &erupt::DeviceLoader, allocation: &Allocation) {
+        let mut blocks = self.blocks.borrow_mut();
+        let slot = &mut blocks[allocation.block];
+        let block = slot.as_mut().unwrap();
+        block.free.free(allocation.offset, allocation.size);
+        block.allocations -= 1;
+        if block.dedicated {
+            unsafe { device.free_memory(Some(block.memory), None) }
+            *slot = None
+        }
+    }
+
+    // Frees every block. Must be called before the device is destroyed, once nothing is allocated.
+    pub(super) fn destroy(&self, device: &erupt::DeviceLoader) {
+        for block in self.blocks.borrow_mut().drain(..).flatten() {
+            debug_assert_eq!(block.allocations, 0);
+            unsafe { device.free_memory(Some(block.memory), None) }
+        }
+    }
+
+    pub fn stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats::default();
+        for block in self.blocks.borrow().iter().flatten() {
+            stats.blocks += 1;
+            stats.allocations += block.allocations;
+            stats.reserved += block.size;
+            stats.used += block.size - block.free.total();
+            stats.free_ranges += block.free.len();
+            stats.largest_free_range = stats.largest_free_range.max(block.free.largest());
+        }
+        stats
+    }
+}
+
+impl Block {
+    fn allocation(&self, block: usize, offset: u64, size: u64) -> Allocation {
+        Allocation {
+            memory: self.memory,
+            block,
+            offset,
+            size,
+            mapped: if self.mapped.is_null() {
+                std::ptr::null_mut()
+            } else {
+                unsafe { self.mapped.add(offset as usize) }
+            },
+        }
+    }
+}
+
+// How much device memory the allocator holds, and how scattered its free space is.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct MemoryStats {
+    pub blocks: usize,
+    pub allocations: usize,
+    pub reserved: u64, // bytes in blocks
+    pub used: u64,     // bytes in allocations
+    pub free_ranges: usize,
+    pub largest_free_range: u64,
+}
+
+impl MemoryStats {
+    // 0 when all the free space is in one range, approaching 1 as it splits into many small ones
+    // that larger allocations can't use.
+    pub fn fragmentation(&self) -> f64 {
+        let free = self.reserved - self.used;
+        if free == 0 {
+            0.
+        } else {
+            1. - self.largest_free_range as f64 / free as f64
+        }
+    }
+}
+
+impl std::fmt::Display for MemoryStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        const MIB: f64 = (1 << 20) as f64;
+        write!(
+            f,
+            "{} allocations in {} blocks, {:.1} of {:.1} MiB used, {} free ranges, {:.0}% fragmented",
+            self.allocations,
+            self.blocks,
+            self.used as f64 / MIB,
+            self.reserved as f64 / MIB,
+            self.free_ranges,
+            self.fragmentation() * 100.
+        )
+    }
+}
+
+// The free ranges of some space, handed out first-fit.
+pub struct FreeList {
+    ranges: Vec<(u64, u64)>, // (offset, size), sorted by offset, never adjacent
+}
+
+impl FreeList {
+    // All of `0..size` starts out free.
+    pub fn new(size: u64) -> Self {
+        let mut list = Self { ranges: Vec::new() };
+        list.free(0, size);
+        list
+    }
+
+    // Takes `size` from the first range it fits in at a multiple of `alignment`, which must be a
+    // power of two. Any space skipped for alignment stays free.
+    pub fn allocate(&mut self, size: u64, alignment: u64) -> Option<u64> {
+        let (index, aligned) =
+            self.ranges
+                .iter()
+                .enumerate()
+                .find_map(|(index, &(offset, length))| {
+                    let aligned = (offset + alignment - 1) & !(alignment - 1);
+                    if aligned + size <= offset + length {
+                        Some((index, aligned))
+                    } else {
+                        None
+                    }
+                })?;
+
+        let (offset, length) = self.ranges.remove(index);
+        let end = offset + length;
+        if aligned + size < end {
+            self.ranges
+                .insert(index, (aligned + size, end - aligned - size));
+        }
+        if offset < aligned {
+            self.ranges.insert(index, (offset, aligned - offset));
+        }
+        Some(aligned)
+    }
+
+    // Gives back `offset..offset + size`, which must not be free already.
+    pub fn free(&mut self, offset: u64, size: u64) {
+        if size == 0 {
+            return;
+        }
+        let index = self.ranges.partition_point(|&(start, _)| start < offset);
+        self.ranges.insert(index, (offset, size));
+
+        // Merge with the following range, then the preceding one.
+        if index + 1 < self.ranges.len() {
+            let (next, next_size) = self.ranges[index + 1];
+            if offset + size == next {
+                self.ranges[index].1 += next_size;
+                self.ranges.remove(index + 1);
+            }
+        }
+        if index > 0 {
+            let (previous, previous_size) = self.ranges[index - 1];
+            if previous + previous_size == offset {
+                self.ranges[index - 1].1 += self.ranges[index].1;
+                self.ranges.remove(index);
+            }
+        }
+    }
+
+    // How much is free right before `end`.
+    pub fn free_before(&self, end: u64) -> u64 {
+        match self.ranges.last() {
+            Some(&(offset, size)) if offset + size == end => size,
+            _ => 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.ranges.iter().map(|&(_, size)| size).sum()
+    }
+
+    pub fn largest(&self) -> u64 {
+        self.ranges.iter().map(|&(_, size)| size).max().unwrap_or(0)
+    }
+}
+
+// A buffer bound to memory from the instance's allocator, destroyed and freed on drop.
+pub struct Buffer {
+    instance: std::rc::Rc<super::Instance>,
+    buffer: vk::Buffer,
+    allocation: Allocation,
+}
+
+impl Buffer {
+    pub fn new(
+        instance: std::rc::Rc<super::Instance>,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        properties: vk::MemoryPropertyFlags,
+    ) -> Result<Self, super::RenderError> {
+        let device = instance.device();
+        let buffer_create_info = vk::BufferCreateInfoBuilder::new()
+            .size(size)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        let buffer = unsafe { device.create_buffer(&buffer_create_info, None) }.result()?;
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+        let allocation =
+            match instance
+                .allocator()
+                .allocate(device, Kind::Buffer, properties, requirements)
+            {
+                Ok(allocation) => allocation,
+                Err(e) => {
+                    unsafe { device.destroy_buffer(Some(buffer), None) }
+                    return Err(e);
+                }
+            };
+        // From here on, dropping the buffer cleans up.
+        let buffer = Self {
+            instance,
+            buffer,
+            allocation,
+        };
+        let device = buffer.instance.device();
+        unsafe {
+            device.bind_buffer_memory(
+                buffer.buffer,
+                buffer.allocation.memory,
+                buffer.allocation.offset,
+            )
+        }
+        .result()?;
+        Ok(buffer)
+    }
+
+    pub fn handle(&self) -> vk::Buffer {
+        self.buffer
+    }
+
+    // Where the buffer's memory is mapped, if it was allocated host-visible.
+    pub fn mapped(&self) -> Option<*mut u8> {
+        Some(self.allocation.mapped).filter(|mapped| !mapped.is_null())
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        let device = self.instance.device();
+        unsafe { device.destroy_buffer(Some(self.buffer), None) }
+        self.instance.allocator().free(device, &self.allocation)
+    }
+}
+
+// An image in device-local memory from the instance's allocator, with a view of all its mip levels
+// and layers. Both are destroyed and the memory freed on drop.
+pub struct Image {
+    instance: std::rc::Rc<super::Instance>,
+    image: vk::Image,
+    view: vk::ImageView,
+    allocation: Allocation,
+}
+
+impl Image {
+    pub fn new(
+        instance: std::rc::Rc<super::Instance>,
+        image_create_info: &vk::ImageCreateInfoBuilder,
+        view_type: vk::ImageViewType,
+        aspect_mask: vk::ImageAspectFlags,
+    ) -> Result<Self, super::RenderError> {
+        let device = instance.device();
+        let image = unsafe { device.create_image(image_create_info, None) }.result()?;
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+        let allocation = match instance.allocator().allocate(
+            device,
+            Kind::Image,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            requirements,
+        ) {
+            Ok(allocation) => allocation,
+            Err(e) => {
+                unsafe { device.destroy_image(Some(image), None) }
+                return Err(e);
+            }
+        };
+        // From here on, dropping the image cleans up. Destroying a null view does nothing.
+        let mut image = Self {
+            instance,
+            image,
+            view: vk::ImageView::null(),
+            allocation,
+        };
+        let device = image.instance.device();
+        unsafe {
+            device.bind_image_memory(
+                image.image,
+                image.allocation.memory,
+                image.allocation.offset,
+            )
+        }
+        .result()?;
+
+        let image_view_create_info = vk::ImageViewCreateInfoBuilder::new()
+            .image(image.image)
+            .view_type(view_type)
+            .format(image_create_info.format)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask,
+                base_mip_level: 0,
+                level_count: image_create_info.mip_levels,
+                base_array_layer: 0,
+                layer_count: image_create_info.array_layers,
+            });
+        image.view = unsafe { device.create_image_view(&image_view_create_info, None) }.result()?;
+        Ok(image)
+    }
+
+    // A single-sampled 2D image, like an attachment.
+    pub fn new_2d(
+        instance: std::rc::Rc<super::Instance>,
+        format: vk::Format,
+        usage: vk::ImageUsageFlags,
+        aspect_mask: vk::ImageAspectFlags,
+        extent: vk::Extent2D,
+    ) -> Result<Self, super::RenderError> {
+        let image_create_info = vk::ImageCreateInfoBuilder::new()
+            .image_type(vk::ImageType::_2D)
+            .extent(vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(1)
+            .format(format)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(usage)
+            .samples(vk::SampleCountFlagBits::_1)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        Self::new(
+            instance,
+            &image_create_info,
+            vk::ImageViewType::_2D,
+            aspect_mask,
+        )
+    }
+
+    pub fn handle(&self) -> vk::Image {
+        self.image
+    }
+
+    pub fn view(&self) -> vk::ImageView {
+        self.view
+    }
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        let device = self.instance.device();
+        unsafe {
+            device.destroy_image_view(Some(self.view), None);
+            device.destroy_image(Some(self.image), None)
+        }
+        self.instance.allocator().free(device, &self.allocation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FreeList, MemoryStats};
+
+    #[test]
+    fn aligned_allocation() {
+        let mut list = FreeList::new(1024);
+        assert_eq!(list.allocate(10, 1), Some(0));
+        assert_eq!(list.allocate(16, 64), Some(64));
+        assert_eq!(list.ranges, [(10, 54), (80, 944)]);
+        // First fit, in the space skipped for alignment.
+        assert_eq!(list.allocate(40, 8), Some(16));
+        assert_eq!(list.ranges, [(10, 6), (56, 8), (80, 944)]);
+        assert_eq!(list.allocate(8, 8), Some(56));
+        assert_eq!(list.ranges, [(10, 6), (80, 944)]);
+    }
+
+    #[test]
+    fn merging() {
+        let full = |size| {
+            let mut list = FreeList::new(size);
+            assert_eq!(list.allocate(size, 1), Some(0));
+            list
+        };
+
+        // Into the following range, then into the preceding one.
+        let mut list = full(300);
+        list.free(100, 100);
+        list.free(0, 100);
+        assert_eq!(list.ranges, [(0, 200)]);
+        list.free(200, 100);
+        assert_eq!(list.ranges, [(0, 300)]);
+
+        // Into both at once.
+        let mut list = full(300);
+        list.free(0, 100);
+        list.free(200, 100);
+        assert_eq!(list.len(), 2);
+        list.free(100, 100);
+        assert_eq!(list.ranges, [(0, 300)]);
+
+        // Not into ranges with a gap in between.
+        let mut list = full(300);
+        list.free(0, 100);
+        list.free(150, 50);
+        assert_eq!(list.ranges, [(0, 100), (150, 50)]);
+        list.free(0, 0);
+        assert_eq!(list.len(), 2);
+    }
+
+    #[test]
+    fn exhaustion() {
+        let mut list = FreeList::new(256);
+        assert_eq!(list.allocate(257, 1), None);
+        assert_eq!(list.allocate(256, 1), Some(0));
+        assert_eq!(list.allocate(1, 1), None);
+        assert_eq!((list.len(), list.total(), list.largest()), (0, 0, 0));
+
+        // Space that only fits without the alignment isn't taken.
+        let mut list = FreeList::new(100);
+        assert_eq!(list.allocate(10, 1), Some(0));
+        assert_eq!(list.allocate(64, 64), None);
+        assert_eq!(list.ranges, [(10, 90)]);
+        assert_eq!(list.free_before(100), 90);
+        assert_eq!(list.allocate(90, 1), Some(10));
+        assert_eq!(list.free_before(100), 0);
+    }
+
+    #[test]
+    fn fragmentation() {
+        let stats = |used, largest_free_range| MemoryStats {
+            reserved: 100,
+            used,
+            largest_free_range,
+            ..MemoryStats::default()
+        };
+        assert_eq!(stats(100, 0).fragmentation(), 0.);
+        assert_eq!(stats(40, 60).fragmentation(), 0.);
+        assert_eq!(stats(40, 30).fragmentation(), 0.5);
+        assert_eq!(stats(0, 10).fragmentation(), 0.9);
+        assert_eq!(MemoryStats::default().fragmentation(), 0.);
+    }
+}
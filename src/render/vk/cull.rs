@@ -0,0 +1,146 @@
+use erupt::vk;
+
+// Runs `cull.comp`, which tests every chunk in the chunk table against the view frustum and writes
+// an indirect draw command for each, so the main pass draws only what's in view without the CPU
+// touching each chunk.
+pub struct CullPipeline {
+    instance: std::rc::Rc<super::Instance>,
+    layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+}
+
+// Matches the push constants in cull.comp.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct CullConstants {
+    planes: [ultraviolet::Vec4; 6],
+    chunk_count: u32,
+    first_command: u32,
+}
+
+impl CullPipeline {
+    const GROUP_SIZE: u32 = 64; // local_size_x in cull.comp
+
+    pub fn new(
+        instance: std::rc::Rc<super::Instance>,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<Self, super::RenderError> {
+        let (layout, pipeline) = Self::create_pipeline(&instance, descriptor_set_layout)?;
+        Ok(Self {
+            instance,
+            layout,
+            pipeline,
+        })
+    }
+
+    fn create_pipeline(
+        instance: &super::Instance,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<(vk::PipelineLayout, vk::Pipeline), super::RenderError> {
+        let device = instance.device();
+
+        let comp_shader_module =
+            instance.create_shader_module("cull.comp", include_shader!("cull.comp"))?;
+
+        let entry_point = std::ffi::CString::new("main").unwrap();
+
+        let stage = vk::PipelineShaderStageCreateInfoBuilder::new()
+            .stage(vk::ShaderStageFlagBits::COMPUTE)
+            .module(*comp_shader_module)
+            .name(&entry_point);
+
+        let push_constant_ranges = [vk::PushConstantRangeBuilder::new()
+            .offset(0)
+            .size(std::mem::size_of::<CullConstants>() as u32)
+            .stage_flags(vk::ShaderStageFlags::COMPUTE)];
+
+        let layout_info = vk::PipelineLayoutCreateInfoBuilder::new()
+            .set_layouts(std::slice::from_ref(&descriptor_set_layout))
+            .push_constant_ranges(&push_constant_ranges);
+
+        let layout = super::Owned::new(
+            device,
+            unsafe { device.create_pipeline_layout(&layout_info, None) }.result()?,
+        );
+
+        let pipeline_create_info = vk::ComputePipelineCreateInfoBuilder::new()
+            .stage(*stage)
+            .layout(*layout);
+
+        let pipeline =
+            unsafe { device.create_compute_pipelines(None, &[pipeline_create_info], None) }
+                .result()?[0];
+
+        Ok((layout.keep(), pipeline))
+    }
+
+    // Writes the commands `first_command` to `first_command + chunk_count` of the bound descriptor
+    // set's command buffer, one per chunk table entry, and counts the drawn and culled chunks. The
+    // commands are ready for indirect draws, and the counts for the host once the frame is done.
+    pub fn record(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        descriptor_set: vk::DescriptorSet,
+        frustum: &crate::render::Frustum,
+        chunk_count: u32,
+        first_command: u32,
+    ) {
+        let device = self.instance.device();
+        let constants = CullConstants {
+            planes: *frustum.planes(),
+            chunk_count,
+            first_command,
+        };
+        let barrier = vk::MemoryBarrierBuilder::new()
+            .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(vk::AccessFlags::INDIRECT_COMMAND_READ | vk::AccessFlags::HOST_READ);
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                self.pipeline,
+            );
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                self.layout,
+                0,
+                &[descriptor_set],
+                &[],
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.layout,
+                vk::ShaderStageFlags::COMPUTE,
+                0,
+                std::mem::size_of::<CullConstants>() as u32,
+                (&constants as *const CullConstants).cast(),
+            );
+            device.cmd_dispatch(
+                command_buffer,
+                (chunk_count + Self::GROUP_SIZE - 1) / Self::GROUP_SIZE,
+                1,
+                1,
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::DRAW_INDIRECT | vk::PipelineStageFlags::HOST,
+                None,
+                &[barrier],
+                &[],
+                &[],
+            )
+        }
+    }
+}
+
+impl Drop for CullPipeline {
+    fn drop(&mut self) {
+        let device = self.instance.device();
+        unsafe {
+            device.destroy_pipeline_layout(Some(self.layout), None);
+            device.destroy_pipeline(Some(self.pipeline), None)
+        }
+    }
+}
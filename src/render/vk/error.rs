@@ -0,0 +1,74 @@
+use erupt::vk;
+
+// Why the renderer couldn't start or draw a frame. Failures not worth telling apart are kept as the
+// Vulkan result they came from.
+#[derive(Debug)]
+pub enum RenderError {
+    Loader(String),
+    MissingValidationLayer,
+    NoSuitableDevice,
+    DeviceNotFound(String),
+    UnsuitableDevice(String),
+    OutOfMemory,
+    NoSuitableMemoryType(vk::MemoryPropertyFlags),
+    DeviceLost,
+    ShaderCreation(&'static str),
+    Vulkan(vk::Result),
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Loader(message) => write!(f, "failed to load Vulkan: {}", message),
+            Self::MissingValidationLayer => write!(
+                f,
+                "the Vulkan validation layer is not installed (needed by debug builds)"
+            ),
+            Self::NoSuitableDevice => write!(f, "no GPU supports graphics and presentation"),
+            Self::DeviceNotFound(device) => write!(f, "no GPU matches {}", device),
+            Self::UnsuitableDevice(device) => write!(
+                f,
+                "the GPU matching {} does not support graphics and presentation",
+                device
+            ),
+            Self::OutOfMemory => write!(f, "out of GPU or host memory"),
+            Self::NoSuitableMemoryType(properties) => write!(
+                f,
+                "no GPU memory type with {:?} fits the resource",
+                properties
+            ),
+            Self::DeviceLost => write!(f, "the GPU device was lost"),
+            Self::ShaderCreation(name) => write!(f, "failed to create shader {}", name),
+            Self::Vulkan(result) => write!(f, "Vulkan call failed: {}", result),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+impl From<vk::Result> for RenderError {
+    fn from(result: vk::Result) -> Self {
+        match result {
+            vk::Result::ERROR_OUT_OF_HOST_MEMORY | vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => {
+                Self::OutOfMemory
+            }
+            vk::Result::ERROR_DEVICE_LOST => Self::DeviceLost,
+            result => Self::Vulkan(result),
+        }
+    }
+}
+
+impl From<erupt::LoaderError> for RenderError {
+    fn from(e: erupt::LoaderError) -> Self {
+        match e {
+            erupt::LoaderError::VulkanError(result) => result.into(),
+            e => Self::Loader(e.to_string()),
+        }
+    }
+}
+
+impl From<erupt::utils::loading::EntryLoaderError> for RenderError {
+    fn from(e: erupt::utils::loading::EntryLoaderError) -> Self {
+        Self::Loader(e.to_string())
+    }
+}
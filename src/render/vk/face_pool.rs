@@ -0,0 +1,174 @@
+use erupt::vk;
+
+// A range of faces in a `FacePool`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FaceRange {
+    pub offset: u32,
+    pub length: u32,
+}
+
+// One device-local vertex buffer holding the faces of every chunk, so they can all be drawn without
+// rebinding. Ranges are handed out first-fit from a free list, and the buffer doubles when nothing
+// fits. Inserted faces are staged on the CPU until `record_uploads` copies them over.
+pub struct FacePool {
+    instance: std::rc::Rc<super::Instance>,
+    buffer: super::Buffer,
+    capacity: usize,
+    free: super::FreeList, // in faces
+    staged: Vec<crate::voxel::Face>,
+    copies: Vec<vk::BufferCopyBuilder<'static>>, // from `staged` to `buffer`
+    // Set once the staged faces are recorded, so they're only dropped when the pool is next
+    // changed, and are kept for the next frame if it turns out nothing was recorded.
+    uploaded: std::cell::Cell<bool>,
+}
+
+impl FacePool {
+    const INITIAL_CAPACITY: usize = 1 << 16;
+    const FACE_SIZE: u64 = std::mem::size_of::<crate::voxel::Face>() as u64;
+
+    pub fn new(instance: std::rc::Rc<super::Instance>) -> Result<Self, super::RenderError> {
+        Ok(Self {
+            buffer: Self::create_buffer(instance.clone(), Self::INITIAL_CAPACITY)?,
+            instance,
+            capacity: Self::INITIAL_CAPACITY,
+            free: super::FreeList::new(Self::INITIAL_CAPACITY as u64),
+            staged: Vec::new(),
+            copies: Vec::new(),
+            uploaded: std::cell::Cell::new(false),
+        })
+    }
+
+    fn create_buffer(
+        instance: std::rc::Rc<super::Instance>,
+        capacity: usize,
+    ) -> Result<super::Buffer, super::RenderError> {
+        super::Buffer::new(
+            instance,
+            capacity as u64 * Self::FACE_SIZE,
+            vk::BufferUsageFlags::VERTEX_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_SRC
+                | vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )
+    }
+
+    pub fn buffer(&self) -> vk::Buffer {
+        self.buffer.handle()
+    }
+
+    // Allocates a free range for `faces` and stages them to be uploaded into it. If the pool has to
+    // grow, this waits for the device to go idle.
+    pub fn insert(
+        &mut self,
+        faces: &[crate::voxel::Face],
+    ) -> Result<FaceRange, super::RenderError> {
+        self.forget_uploaded();
+
+        let length = faces.len() as u64;
+        let offset = match self.free.allocate(length, 1) {
+            Some(offset) => offset,
+            None => {
+                self.grow(length)?;
+                self.free.allocate(length, 1).unwrap()
+            }
+        };
+        let range = FaceRange {
+            offset: offset as u32,
+            length: length as u32,
+        };
+
+        self.copies.push(
+            vk::BufferCopyBuilder::new()
+                .src_offset(self.staged.len() as u64 * Self::FACE_SIZE)
+                .dst_offset(range.offset as u64 * Self::FACE_SIZE)
+                .size(faces.len() as u64 * Self::FACE_SIZE),
+        );
+        self.staged.extend_from_slice(faces);
+        Ok(range)
+    }
+
+    // Returns a range to the free list. It must no longer be used by any frame in flight.
+    pub fn remove(&mut self, range: FaceRange) {
+        self.free.free(range.offset as u64, range.length as u64)
+    }
+
+    // How many faces are waiting to be uploaded, which is how much staging space
+    // `record_uploads` will need.
+    pub fn staged_len(&mut self) -> usize {
+        self.forget_uploaded();
+        self.staged.len()
+    }
+
+    // Copies the staged faces through `staging`, which must hold at least `staged_len` faces and
+    // not be in use by any frame in flight. Draws recorded afterwards see the new faces.
+    pub fn record_uploads(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        staging: &super::HostBuffer<crate::voxel::Face>,
+    ) {
+        if self.copies.is_empty() {
+            return;
+        }
+        staging.write(0, &self.staged);
+
+        let device = self.instance.device();
+        let barrier = vk::MemoryBarrierBuilder::new()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::VERTEX_ATTRIBUTE_READ);
+        unsafe {
+            device.cmd_copy_buffer(
+                command_buffer,
+                staging.buffer(),
+                self.buffer.handle(),
+                &self.copies,
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::VERTEX_INPUT,
+                None,
+                &[barrier],
+                &[],
+                &[],
+            )
+        }
+        self.uploaded.set(true)
+    }
+
+    fn forget_uploaded(&mut self) {
+        if self.uploaded.replace(false) {
+            self.staged.clear();
+            self.copies.clear()
+        }
+    }
+
+    // Doubles the capacity until at least `length` more faces fit at the end, copying the old
+    // contents over on the GPU.
+    fn grow(&mut self, length: u64) -> Result<(), super::RenderError> {
+        let old_capacity = self.capacity as u64;
+        let tail = self.free.free_before(old_capacity);
+        let mut capacity = old_capacity * 2;
+        while capacity - old_capacity + tail < length {
+            capacity *= 2
+        }
+
+        self.instance.wait_idle()?;
+        let buffer = Self::create_buffer(self.instance.clone(), capacity as usize)?;
+        let region = vk::BufferCopyBuilder::new()
+            .src_offset(0)
+            .dst_offset(0)
+            .size(old_capacity * Self::FACE_SIZE);
+        self.instance.submit_once(|command_buffer| unsafe {
+            self.instance.device().cmd_copy_buffer(
+                command_buffer,
+                self.buffer.handle(),
+                buffer.handle(),
+                &[region],
+            )
+        })?;
+        self.buffer = buffer;
+        self.capacity = capacity as usize;
+        self.free.free(old_capacity, capacity - old_capacity);
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use erupt::vk;
+
+// A buffer of `capacity` values of `T` in host-visible, coherent memory, mapped for its whole
+// lifetime. Writes are seen by commands submitted after them, so callers only have to make sure
+// the GPU isn't reading the values they overwrite.
+pub struct HostBuffer<T> {
+    buffer: super::Buffer,
+    mapped: *mut T,
+    capacity: usize,
+}
+
+impl<T: Copy> HostBuffer<T> {
+    pub fn new(
+        instance: std::rc::Rc<super::Instance>,
+        usage: vk::BufferUsageFlags,
+        capacity: usize,
+    ) -> Result<Self, super::RenderError> {
+        // Buffers can't be empty.
+        let capacity = capacity.max(1);
+        let buffer = super::Buffer::new(
+            instance,
+            (capacity * std::mem::size_of::<T>()) as u64,
+            usage,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+        Ok(Self {
+            mapped: buffer.mapped().unwrap().cast(),
+            buffer,
+            capacity,
+        })
+    }
+
+    pub fn buffer(&self) -> vk::Buffer {
+        self.buffer.handle()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Copies `values` into the buffer, starting at index `offset`.
+    pub fn write(&self, offset: usize, values: &[T]) {
+        assert!(offset + values.len() <= self.capacity);
+        unsafe {
+            std::ptr::copy_nonoverlapping(values.as_ptr(), self.mapped.add(offset), values.len())
+        }
+    }
+
+    pub fn read(&self, index: usize) -> T {
+        assert!(index < self.capacity);
+        unsafe { self.mapped.add(index).read() }
+    }
+}
@@ -4,7 +4,8 @@ pub struct Instance {
     messenger: vk::DebugUtilsMessengerEXT,
     surface: vk::SurfaceKHR,
     physical_device: vk::PhysicalDevice,
-    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    allocator: super::Allocator,
+    multi_draw_indirect: bool,
     graphics: super::QueueInfo,
     present: super::QueueInfo,
 
@@ -16,18 +17,46 @@ pub struct Instance {
 }
 
 impl Instance {
-    pub fn new(window: &crate::window::Window) -> std::rc::Rc<Self> {
-        let entry = erupt::EntryLoader::new().unwrap();
-        let mut instance_extensions =
-            erupt::utils::surface::enumerate_required_extensions(window.window()).unwrap();
+    // Without a `device`, the first discrete GPU is picked, then integrated, then anything.
+    pub fn new(
+        window: &crate::window::Window,
+        device: Option<&super::DeviceSelector>,
+    ) -> Result<std::rc::Rc<Self>, super::RenderError> {
+        Self::create(Some(window), device)
+    }
+
+    // An instance without a surface, which can only render to an `Offscreen` target.
+    pub fn headless(
+        device: Option<&super::DeviceSelector>,
+    ) -> Result<std::rc::Rc<Self>, super::RenderError> {
+        Self::create(None, device)
+    }
+
+    fn create(
+        window: Option<&crate::window::Window>,
+        device: Option<&super::DeviceSelector>,
+    ) -> Result<std::rc::Rc<Self>, super::RenderError> {
+        let entry = erupt::EntryLoader::new()?;
+        let (mut instance_extensions, device_extensions) = match window {
+            Some(window) => (
+                erupt::utils::surface::enumerate_required_extensions(window.window()).result()?,
+                vec![vk::KHR_SWAPCHAIN_EXTENSION_NAME],
+            ),
+            None => (Vec::new(), Vec::new()),
+        };
         let mut instance_layers = Vec::new();
-        let device_extensions = vec![vk::KHR_SWAPCHAIN_EXTENSION_NAME];
         let mut device_layers = Vec::new();
 
         // Create Instance & Debug Messenger
 
         let (instance, messenger) = if crate::DEBUG_MODE {
             let val = erupt::cstr!("VK_LAYER_KHRONOS_validation");
+            let layers = unsafe { entry.enumerate_instance_layer_properties(None) }.result()?;
+            if !layers.iter().any(|layer| unsafe {
+                std::ffi::CStr::from_ptr(layer.layer_name.as_ptr()) == std::ffi::CStr::from_ptr(val)
+            }) {
+                return Err(super::RenderError::MissingValidationLayer);
+            }
             instance_extensions.push(vk::EXT_DEBUG_UTILS_EXTENSION_NAME);
             instance_layers.push(val);
             device_layers.push(val);
@@ -41,11 +70,11 @@ impl Instance {
                 .extend_from(&mut messenger_create_info);
             let instance = unsafe {
                 erupt::InstanceLoader::new(&entry, &instance_create_info, None)
-            }.unwrap();
+            }?;
             let messenger = unsafe {
                 instance.create_debug_utils_messenger_ext(&messenger_create_info, None)
             }
-            .unwrap();
+            .result()?;
             (instance, messenger)
         } else {
             let instance_create_info = vk::InstanceCreateInfoBuilder::new()
@@ -53,60 +82,102 @@ impl Instance {
                 .enabled_layer_names(&instance_layers);
             let instance = unsafe {
                 erupt::InstanceLoader::new(&entry, &instance_create_info, None)
-            }.unwrap();
+            }?;
             (instance, vk::DebugUtilsMessengerEXT::null())
         };
 
         // Create Surface
 
-        let surface =
-            unsafe { erupt::utils::surface::create_surface(&instance, window.window(), None) }.unwrap();
+        let surface = match window {
+            Some(window) => unsafe {
+                erupt::utils::surface::create_surface(&instance, window.window(), None)
+            }
+            .result()?,
+            None => vk::SurfaceKHR::null(),
+        };
 
         // Find Physical Device & Queue Families
 
-        let physical_devices = unsafe { instance.enumerate_physical_devices(None) }.unwrap();
-        let (physical_device, _, graphics_family, present_family) = physical_devices
+        let mut adapters = super::Adapter::enumerate(&instance)?;
+        if let Some(device) = device {
+            adapters.retain(|adapter| device.matches(adapter));
+            adapters.truncate(1);
+            if adapters.is_empty() {
+                return Err(super::RenderError::DeviceNotFound(device.to_string()));
+            }
+        }
+        let (adapter, graphics_family, present_family) = adapters
             .into_iter()
-            .filter_map(|physical_device| {
-                let properties =
-                    unsafe { instance.get_physical_device_properties(physical_device) };
+            .filter_map(|adapter| {
+                let physical_device = adapter.physical_device();
                 let queue_families = unsafe {
                     instance.get_physical_device_queue_family_properties(physical_device, None)
                 };
 
-                let present_family = match (0..queue_families.len()).find(|&index| {
-                    unsafe {
-                        instance.get_physical_device_surface_support_khr(
-                            physical_device,
-                            index as u32,
-                            surface,
-                        )
-                    }
-                    .unwrap()
-                }) {
-                    Some(index) => index as u32,
-                    None => return None,
-                };
+                // Compute culling runs on the graphics queue, so prefer a family that does both.
                 let graphics_family = match queue_families
-                    .into_iter()
-                    .position(|family| family.queue_flags.contains(vk::QueueFlags::GRAPHICS))
-                {
+                    .iter()
+                    .position(|family| {
+                        family
+                            .queue_flags
+                            .contains(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+                    })
+                    .or_else(|| {
+                        queue_families.iter().position(|family| {
+                            family.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+                        })
+                    }) {
                     Some(index) => index as u32,
                     None => return None,
                 };
-                Some((physical_device, properties, graphics_family, present_family))
+                let present_family = if surface.is_null() {
+                    graphics_family
+                } else {
+                    match (0..queue_families.len()).find(|&index| {
+                        unsafe {
+                            instance.get_physical_device_surface_support_khr(
+                                physical_device,
+                                index as u32,
+                                surface,
+                            )
+                        }
+                        .result()
+                        .unwrap_or(false)
+                    }) {
+                        Some(index) => index as u32,
+                        None => return None,
+                    }
+                };
+                Some((adapter, graphics_family, present_family))
             })
-            .min_by_key(|(_, properties, ..)| match properties.device_type {
+            .min_by_key(|(adapter, ..)| match adapter.device_type() {
                 vk::PhysicalDeviceType::DISCRETE_GPU => 0,
                 vk::PhysicalDeviceType::INTEGRATED_GPU => 1,
                 _ => 2,
             })
-            .unwrap();
+            .ok_or_else(|| match device {
+                Some(device) => super::RenderError::UnsuitableDevice(device.to_string()),
+                None => super::RenderError::NoSuitableDevice,
+            })?;
+        adapter.log_report();
+        let physical_device = adapter.physical_device();
 
         // Get Physical Device Memory Properties
 
-        let memory_properties =
-            unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let memory_properties = adapter.memory_properties();
+
+        // Enable Optional Features
+
+        // Drawing every chunk with one indirect draw needs both of these; without them, chunks are
+        // drawn one by one.
+        let supported_features = adapter.features();
+        let multi_draw_indirect = supported_features.multi_draw_indirect != vk::FALSE
+            && supported_features.draw_indirect_first_instance != vk::FALSE;
+        let mut enabled_features = vk::PhysicalDeviceFeatures::default();
+        if multi_draw_indirect {
+            enabled_features.multi_draw_indirect = vk::TRUE;
+            enabled_features.draw_indirect_first_instance = vk::TRUE;
+        }
 
         // Create Logical Device & Queues
 
@@ -124,10 +195,11 @@ impl Instance {
         let device_create_info = vk::DeviceCreateInfoBuilder::new()
             .enabled_extension_names(&device_extensions)
             .enabled_layer_names(&device_layers)
+            .enabled_features(&enabled_features)
             .queue_create_infos(&queue_create_infos);
         let device = unsafe {
             erupt::DeviceLoader::new(&instance, physical_device, &device_create_info, None)
-        }.unwrap();
+        }?;
         let graphics_queue = unsafe { device.get_device_queue(graphics_family, 0) };
         let present_queue = unsafe { device.get_device_queue(present_family, 0) };
 
@@ -139,7 +211,8 @@ impl Instance {
             messenger,
             surface,
             physical_device,
-            memory_properties,
+            allocator: super::Allocator::new(memory_properties),
+            multi_draw_indirect,
             graphics: super::QueueInfo {
                 family: graphics_family,
                 queue: graphics_queue,
@@ -149,7 +222,7 @@ impl Instance {
                 queue: present_queue,
             },
         };
-        std::rc::Rc::new(ret)
+        Ok(std::rc::Rc::new(ret))
     }
 
     pub fn device(&self) -> &erupt::DeviceLoader {
@@ -161,15 +234,23 @@ impl Instance {
     pub(super) fn present(&self) -> super::QueueInfo {
         self.present
     }
+    // Whether the `multiDrawIndirect` and `drawIndirectFirstInstance` features are enabled.
+    pub(super) fn multi_draw_indirect(&self) -> bool {
+        self.multi_draw_indirect
+    }
 
-    pub(super) fn surface_info(&self, (width, height): (u32, u32)) -> super::SurfaceInfo {
+    pub(super) fn surface_info(
+        &self,
+        (width, height): (u32, u32),
+        present_mode: super::PresentMode,
+    ) -> Result<super::SurfaceInfo, super::RenderError> {
         let surface_caps = unsafe {
             self.instance.get_physical_device_surface_capabilities_khr(
                 self.physical_device,
                 self.surface,
             )
         }
-        .unwrap();
+        .result()?;
 
         let surface_formats = unsafe {
             self.instance.get_physical_device_surface_formats_khr(
@@ -178,7 +259,7 @@ impl Instance {
                 None,
             )
         }
-        .unwrap();
+        .result()?;
         let surface_format = surface_formats
             .iter()
             .cloned()
@@ -195,10 +276,12 @@ impl Instance {
                 None,
             )
         }
-        .unwrap();
-        let present_mode = present_modes
-            .into_iter()
-            .find(|&present_mode| present_mode == vk::PresentModeKHR::MAILBOX_KHR)
+        .result()?;
+        let present_mode = present_mode
+            .candidates()
+            .iter()
+            .copied()
+            .find(|present_mode| present_modes.contains(present_mode))
             .unwrap_or(vk::PresentModeKHR::FIFO_KHR);
 
         let extent = vk::Extent2D {
@@ -212,40 +295,109 @@ impl Instance {
             ),
         };
 
-        super::SurfaceInfo {
+        Ok(super::SurfaceInfo {
             surface: self.surface,
             surface_caps,
             surface_format,
             present_mode,
             extent,
+        })
+    }
+
+    pub(super) fn allocator(&self) -> &super::Allocator {
+        &self.allocator
+    }
+
+    // How much device memory buffers and images are using.
+    pub fn memory_stats(&self) -> super::MemoryStats {
+        self.allocator.stats()
+    }
+
+    // Records commands with `recorder`, submits them to the graphics queue and blocks until they
+    // have finished. Meant for one-off work like uploads, not for per-frame rendering.
+    pub(super) fn submit_once<F: FnOnce(vk::CommandBuffer)>(
+        &self,
+        recorder: F,
+    ) -> Result<(), super::RenderError> {
+        let command_pool_create_info = vk::CommandPoolCreateInfoBuilder::new()
+            .queue_family_index(self.graphics.family)
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT);
+        let command_pool =
+            unsafe { self.device.create_command_pool(&command_pool_create_info, None) }
+                .result()?;
+        let result = self.submit_once_from(command_pool, recorder);
+        unsafe { self.device.destroy_command_pool(Some(command_pool), None) }
+        result
+    }
+
+    fn submit_once_from<F: FnOnce(vk::CommandBuffer)>(
+        &self,
+        command_pool: vk::CommandPool,
+        recorder: F,
+    ) -> Result<(), super::RenderError> {
+        let command_buffer_allocate_info = vk::CommandBufferAllocateInfoBuilder::new()
+            .command_pool(command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+        let command_buffer =
+            unsafe { self.device.allocate_command_buffers(&command_buffer_allocate_info) }
+                .result()?[0];
+
+        let command_buffer_begin_info = vk::CommandBufferBeginInfoBuilder::new()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        unsafe {
+            self.device
+                .begin_command_buffer(command_buffer, &command_buffer_begin_info)
+                .result()?
         }
+        recorder(command_buffer);
+        unsafe { self.device.end_command_buffer(command_buffer) }.result()?;
+
+        let submit_info =
+            vk::SubmitInfoBuilder::new().command_buffers(std::slice::from_ref(&command_buffer));
+        unsafe {
+            self.device
+                .queue_submit(self.graphics.queue, &[submit_info], None)
+                .result()?;
+            self.device.queue_wait_idle(self.graphics.queue).result()?
+        }
+        Ok(())
     }
 
-    pub fn get_memory_type_index(
+    // Creates a shader module from one of the SPIR-V files built from src/shaders, named for
+    // errors. The module is destroyed when the returned handle is dropped.
+    pub(super) fn create_shader_module(
         &self,
-        properties: vk::MemoryPropertyFlags,
-        requirements: vk::MemoryRequirements,
-    ) -> u32 {
-        (0..self.memory_properties.memory_type_count)
-            .find(|i| {
-                (((requirements.memory_type_bits >> i) & 1) != 0)
-                    && self.memory_properties.memory_types[*i as usize]
-                        .property_flags
-                        .contains(properties)
+        name: &'static str,
+        spv: &[u8],
+    ) -> Result<super::Owned<'_, vk::ShaderModule>, super::RenderError> {
+        let code = erupt::utils::decode_spv(spv)
+            .map_err(|_| super::RenderError::ShaderCreation(name))?;
+        let shader_module_create_info = vk::ShaderModuleCreateInfoBuilder::new().code(&code);
+        unsafe { self.device.create_shader_module(&shader_module_create_info, None) }
+            .map_err(|result| match result {
+                vk::Result::ERROR_OUT_OF_HOST_MEMORY | vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => {
+                    super::RenderError::OutOfMemory
+                }
+                _ => super::RenderError::ShaderCreation(name),
             })
-            .unwrap()
+            .map(|shader_module| super::Owned::new(&self.device, shader_module))
     }
 
-    pub fn wait_idle(&self) {
-        unsafe { self.device.device_wait_idle() }.unwrap();
+    pub fn wait_idle(&self) -> Result<(), super::RenderError> {
+        unsafe { self.device.device_wait_idle() }.result()?;
+        Ok(())
     }
 }
 
 impl Drop for Instance {
     fn drop(&mut self) {
+        self.allocator.destroy(&self.device);
         unsafe {
             self.device.destroy_device(None);
-            self.instance.destroy_surface_khr(Some(self.surface), None);
+            if !self.surface.is_null() {
+                self.instance.destroy_surface_khr(Some(self.surface), None)
+            }
             if !self.messenger.is_null() {
                 self.instance
                     .destroy_debug_utils_messenger_ext(Some(self.messenger), None)
@@ -0,0 +1,248 @@
+use erupt::vk;
+
+// A color and depth target that isn't tied to a window, whose color image can be read back after
+// rendering.
+pub struct Offscreen {
+    instance: std::rc::Rc<super::Instance>,
+    render_pass: vk::RenderPass,
+    color_image: super::Image,
+    #[allow(dead_code)]
+    depth_image: super::Image,
+    framebuffer: vk::Framebuffer,
+    readback_buffer: super::Buffer,
+    extent: vk::Extent2D,
+}
+
+impl Offscreen {
+    // Matches the swapchain's preferred sRGB format, with red first for easy readback.
+    const COLOR_FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+
+    pub fn new(
+        instance: std::rc::Rc<super::Instance>,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, super::RenderError> {
+        let device = instance.device();
+        let extent = vk::Extent2D { width, height };
+
+        // Create Render Pass
+
+        let attachments = [
+            vk::AttachmentDescriptionBuilder::new()
+                .format(Self::COLOR_FORMAT)
+                .samples(vk::SampleCountFlagBits::_1)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::STORE)
+                .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .final_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL),
+            vk::AttachmentDescriptionBuilder::new()
+                .format(vk::Format::D32_SFLOAT)
+                .samples(vk::SampleCountFlagBits::_1)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+        ];
+        let depth_stencil_attachment = vk::AttachmentReferenceBuilder::new()
+            .attachment(1)
+            .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
+        let color_attachments = vk::AttachmentReferenceBuilder::new()
+            .attachment(0)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+        let subpass = vk::SubpassDescriptionBuilder::new()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(std::slice::from_ref(&color_attachments))
+            .depth_stencil_attachment(&depth_stencil_attachment);
+        let dependencies = [
+            vk::SubpassDependencyBuilder::new()
+                .src_subpass(vk::SUBPASS_EXTERNAL)
+                .dst_subpass(0)
+                .src_stage_mask(
+                    vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                        | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                )
+                .src_access_mask(vk::AccessFlags::empty())
+                .dst_stage_mask(
+                    vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                        | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                )
+                .dst_access_mask(
+                    vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                        | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                ),
+            // Make the color writes visible to the copy into the readback buffer.
+            vk::SubpassDependencyBuilder::new()
+                .src_subpass(0)
+                .dst_subpass(vk::SUBPASS_EXTERNAL)
+                .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags::TRANSFER)
+                .dst_access_mask(vk::AccessFlags::TRANSFER_READ),
+        ];
+        let render_pass_create_info = vk::RenderPassCreateInfoBuilder::new()
+            .attachments(&attachments)
+            .subpasses(std::slice::from_ref(&subpass))
+            .dependencies(&dependencies);
+        let render_pass = super::Owned::new(
+            device,
+            unsafe { device.create_render_pass(&render_pass_create_info, None) }.result()?,
+        );
+
+        // Create Color & Depth Image Resources
+
+        let color_image = super::Image::new_2d(
+            instance.clone(),
+            Self::COLOR_FORMAT,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+            vk::ImageAspectFlags::COLOR,
+            extent,
+        )?;
+        let depth_image = super::Image::new_2d(
+            instance.clone(),
+            vk::Format::D32_SFLOAT,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            vk::ImageAspectFlags::DEPTH,
+            extent,
+        )?;
+
+        let attachments = [color_image.view(), depth_image.view()];
+        let framebuffer_create_info = vk::FramebufferCreateInfoBuilder::new()
+            .render_pass(*render_pass)
+            .attachments(&attachments)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(1);
+        let framebuffer = super::Owned::new(
+            device,
+            unsafe { device.create_framebuffer(&framebuffer_create_info, None) }.result()?,
+        );
+
+        // Create Readback Buffer
+
+        let readback_buffer = super::Buffer::new(
+            instance.clone(),
+            width as u64 * height as u64 * 4,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+
+        Ok(Self {
+            render_pass: render_pass.keep(),
+            framebuffer: framebuffer.keep(),
+            instance,
+            color_image,
+            depth_image,
+            readback_buffer,
+            extent,
+        })
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.extent.width as f32 / self.extent.height as f32
+    }
+
+    pub(super) fn record<F: FnOnce(vk::CommandBuffer)>(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        f: F,
+    ) {
+        let device = self.instance.device();
+
+        let render_pass_begin_info = vk::RenderPassBeginInfoBuilder::new()
+            .render_pass(self.render_pass)
+            .framebuffer(self.framebuffer)
+            .render_area(vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            })
+            .clear_values(&super::CLEAR_VALUES);
+        unsafe {
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &render_pass_begin_info,
+                vk::SubpassContents::INLINE,
+            );
+        }
+        f(command_buffer);
+
+        let region = vk::BufferImageCopyBuilder::new()
+            .buffer_offset(0)
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            })
+            .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+            .image_extent(vk::Extent3D {
+                width: self.extent.width,
+                height: self.extent.height,
+                depth: 1,
+            });
+        let barrier = vk::BufferMemoryBarrierBuilder::new()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(self.readback_buffer.handle())
+            .offset(0)
+            .size(vk::WHOLE_SIZE);
+        unsafe {
+            device.cmd_end_render_pass(command_buffer);
+            device.cmd_copy_image_to_buffer(
+                command_buffer,
+                self.color_image.handle(),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                self.readback_buffer.handle(),
+                &[region],
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::HOST,
+                None,
+                &[],
+                &[barrier],
+                &[],
+            );
+        }
+    }
+
+    // Must only be called once the commands recorded by `record` have finished executing.
+    pub(super) fn read(&self) -> crate::render::Image {
+        let size = self.extent.width as usize * self.extent.height as usize * 4;
+        let memory = self.readback_buffer.mapped().unwrap();
+        let pixels = unsafe { std::slice::from_raw_parts(memory, size) }.to_vec();
+
+        crate::render::Image::from_rgba(self.extent.width, self.extent.height, pixels)
+    }
+}
+
+impl super::Target for Offscreen {
+    fn instance(&self) -> std::rc::Rc<super::Instance> {
+        self.instance.clone()
+    }
+
+    fn render_info(&self) -> super::RenderInfo {
+        super::RenderInfo {
+            render_pass: self.render_pass,
+            extent: self.extent,
+        }
+    }
+}
+
+impl Drop for Offscreen {
+    fn drop(&mut self) {
+        let device = self.instance.device();
+        unsafe {
+            device.destroy_framebuffer(Some(self.framebuffer), None);
+            device.destroy_render_pass(Some(self.render_pass), None)
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use erupt::vk;
+
+// Draws a wireframe cube around a single voxel.
+pub struct OutlineRenderer {
+    instance: std::rc::Rc<super::Instance>,
+    layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+}
+
+impl OutlineRenderer {
+    const PUSH_CONSTANTS_SIZE: u32 = 80; // mat4 view-projection, vec4 voxel position
+
+    pub fn new<T: super::Target>(target: &T) -> Result<Self, super::RenderError> {
+        let instance = target.instance();
+        let (layout, pipeline) = Self::create_pipeline(&instance, target.render_info())?;
+
+        Ok(Self {
+            instance,
+            layout,
+            pipeline,
+        })
+    }
+
+    fn create_pipeline(
+        instance: &super::Instance,
+        render_info: super::RenderInfo,
+    ) -> Result<(vk::PipelineLayout, vk::Pipeline), super::RenderError> {
+        let device = instance.device();
+
+        let vert_shader_module =
+            instance.create_shader_module("outline.vert", include_shader!("outline.vert"))?;
+
+        let frag_shader_module =
+            instance.create_shader_module("outline.frag", include_shader!("outline.frag"))?;
+
+        let entry_point = std::ffi::CString::new("main").unwrap();
+
+        let stages = [
+            vk::PipelineShaderStageCreateInfoBuilder::new()
+                .stage(vk::ShaderStageFlagBits::VERTEX)
+                .module(*vert_shader_module)
+                .name(&entry_point),
+            vk::PipelineShaderStageCreateInfoBuilder::new()
+                .stage(vk::ShaderStageFlagBits::FRAGMENT)
+                .module(*frag_shader_module)
+                .name(&entry_point),
+        ];
+
+        let vertex_input = vk::PipelineVertexInputStateCreateInfoBuilder::new();
+
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfoBuilder::new()
+            .topology(vk::PrimitiveTopology::LINE_LIST)
+            .primitive_restart_enable(false);
+
+        let viewports = [vk::ViewportBuilder::new()
+            .x(0.0)
+            .y(0.0)
+            .width(render_info.extent.width as f32)
+            .height(render_info.extent.height as f32)
+            .min_depth(0.0)
+            .max_depth(1.0)];
+        let scissors = [vk::Rect2DBuilder::new()
+            .offset(vk::Offset2D { x: 0, y: 0 })
+            .extent(render_info.extent)];
+        let viewport = vk::PipelineViewportStateCreateInfoBuilder::new()
+            .viewports(&viewports)
+            .scissors(&scissors);
+
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfoBuilder::new()
+            .depth_test_enable(true)
+            .depth_write_enable(false)
+            .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL);
+
+        let rasterization = vk::PipelineRasterizationStateCreateInfoBuilder::new()
+            .depth_clamp_enable(false)
+            .rasterizer_discard_enable(false)
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .cull_mode(vk::CullModeFlags::NONE)
+            .front_face(vk::FrontFace::CLOCKWISE)
+            .depth_bias_enable(false);
+        let multisample = vk::PipelineMultisampleStateCreateInfoBuilder::new()
+            .sample_shading_enable(false)
+            .rasterization_samples(vk::SampleCountFlagBits::_1);
+
+        let attachments = [vk::PipelineColorBlendAttachmentStateBuilder::new()
+            .color_write_mask(vk::ColorComponentFlags::all())
+            .blend_enable(false)];
+
+        let color_blend = vk::PipelineColorBlendStateCreateInfoBuilder::new()
+            .logic_op_enable(false)
+            .logic_op(vk::LogicOp::COPY)
+            .attachments(&attachments)
+            .blend_constants([0.0, 0.0, 0.0, 0.0]);
+
+        let push_constant_ranges = [vk::PushConstantRangeBuilder::new()
+            .offset(0)
+            .size(Self::PUSH_CONSTANTS_SIZE)
+            .stage_flags(vk::ShaderStageFlags::VERTEX)];
+
+        let layout_info =
+            vk::PipelineLayoutCreateInfoBuilder::new().push_constant_ranges(&push_constant_ranges);
+
+        let layout = super::Owned::new(
+            device,
+            unsafe { device.create_pipeline_layout(&layout_info, None) }.result()?,
+        );
+
+        let pipeline_create_info = vk::GraphicsPipelineCreateInfoBuilder::new()
+            .stages(&stages)
+            .vertex_input_state(&vertex_input)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport)
+            .depth_stencil_state(&depth_stencil)
+            .rasterization_state(&rasterization)
+            .multisample_state(&multisample)
+            .color_blend_state(&color_blend)
+            .layout(*layout)
+            .render_pass(render_info.render_pass)
+            .subpass(0);
+
+        let pipeline =
+            unsafe { device.create_graphics_pipelines(None, &[pipeline_create_info], None) }
+                .result()?[0];
+
+        Ok((layout.keep(), pipeline))
+    }
+
+    pub fn draw(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        matrix: &ultraviolet::Mat4,
+        [x, y, z]: [i32; 3],
+    ) {
+        let device = self.instance.device();
+        let voxel = ultraviolet::Vec4::new(x as f32, y as f32, z as f32, 0.);
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline,
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.layout,
+                vk::ShaderStageFlags::VERTEX,
+                0,
+                64,
+                matrix.as_ptr().cast(),
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.layout,
+                vk::ShaderStageFlags::VERTEX,
+                64,
+                16,
+                voxel.as_ptr().cast(),
+            );
+            device.cmd_draw(command_buffer, 24, 1, 0, 0)
+        }
+    }
+
+    pub fn rebuild<T: super::Target>(&mut self, target: &T) -> Result<(), super::RenderError> {
+        let (layout, pipeline) = Self::create_pipeline(&self.instance, target.render_info())?;
+        let device = self.instance.device();
+        unsafe {
+            device.destroy_pipeline_layout(Some(self.layout), None);
+            device.destroy_pipeline(Some(self.pipeline), None)
+        }
+        self.layout = layout;
+        self.pipeline = pipeline;
+        Ok(())
+    }
+}
+
+impl Drop for OutlineRenderer {
+    fn drop(&mut self) {
+        let device = self.instance.device();
+        unsafe {
+            device.destroy_pipeline_layout(Some(self.layout), None);
+            device.destroy_pipeline(Some(self.pipeline), None)
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use erupt::vk;
+
+// A handle that's destroyed when dropped, unless `keep` hands it over to whatever destroys it from
+// then on. Constructors hold the handles they create in these, so an error partway through doesn't
+// leak the ones created before it.
+pub struct Owned<'a, T: Destroy> {
+    device: &'a erupt::DeviceLoader,
+    handle: Option<T>,
+}
+
+impl<'a, T: Destroy> Owned<'a, T> {
+    pub fn new(device: &'a erupt::DeviceLoader, handle: T) -> Self {
+        Self {
+            device,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn keep(mut self) -> T {
+        self.handle.take().unwrap()
+    }
+}
+
+impl<T: Destroy> std::ops::Deref for Owned<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.handle.as_ref().unwrap()
+    }
+}
+
+impl<T: Destroy> Drop for Owned<'_, T> {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            unsafe { handle.destroy(self.device) }
+        }
+    }
+}
+
+pub trait Destroy: Copy {
+    unsafe fn destroy(self, device: &erupt::DeviceLoader);
+}
+
+macro_rules! impl_destroy {
+    ($($handle:ty => $destroy:ident,)*) => {
+        $(impl Destroy for $handle {
+            unsafe fn destroy(self, device: &erupt::DeviceLoader) {
+                device.$destroy(Some(self), None)
+            }
+        })*
+    };
+}
+
+impl_destroy! {
+    vk::CommandPool => destroy_command_pool,
+    vk::DescriptorPool => destroy_descriptor_pool,
+    vk::DescriptorSetLayout => destroy_descriptor_set_layout,
+    vk::Fence => destroy_fence,
+    vk::Framebuffer => destroy_framebuffer,
+    vk::ImageView => destroy_image_view,
+    vk::Pipeline => destroy_pipeline,
+    vk::PipelineLayout => destroy_pipeline_layout,
+    vk::RenderPass => destroy_render_pass,
+    vk::Sampler => destroy_sampler,
+    vk::Semaphore => destroy_semaphore,
+    vk::ShaderModule => destroy_shader_module,
+    vk::SwapchainKHR => destroy_swapchain_khr,
+}
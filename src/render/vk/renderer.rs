@@ -8,10 +8,10 @@ pub struct Renderer {
 }
 
 impl Renderer {
-    const MAX_FRAMES_IN_FLIGHT: usize = 2;
+    pub(super) const MAX_FRAMES_IN_FLIGHT: usize = 2;
 
-    pub fn new(surface: &super::Surface) -> Self {
-        let instance = surface.instance();
+    pub fn new<T: super::Target>(target: &T) -> Result<Self, super::RenderError> {
+        let instance = target.instance();
         let device = instance.device();
         let queue = instance.graphics();
 
@@ -20,63 +20,83 @@ impl Renderer {
         let command_pool_create_info = vk::CommandPoolCreateInfoBuilder::new()
             .queue_family_index(queue.family)
             .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER);
-        let command_pool =
-            unsafe { device.create_command_pool(&command_pool_create_info, None) }.unwrap();
+        let command_pool = super::Owned::new(
+            device,
+            unsafe { device.create_command_pool(&command_pool_create_info, None) }.result()?,
+        );
 
         // Create Sync Objects
 
         let command_buffer_allocate_info = vk::CommandBufferAllocateInfoBuilder::new()
-            .command_pool(command_pool)
+            .command_pool(*command_pool)
             .command_buffer_count(Self::MAX_FRAMES_IN_FLIGHT as u32);
         let command_buffers =
-            unsafe { device.allocate_command_buffers(&command_buffer_allocate_info) }.unwrap();
+            unsafe { device.allocate_command_buffers(&command_buffer_allocate_info) }.result()?;
         let semaphore_create_info = vk::SemaphoreCreateInfoBuilder::new();
         let fence_create_info =
             vk::FenceCreateInfoBuilder::new().flags(vk::FenceCreateFlags::SIGNALED);
         let syncs = command_buffers
             .into_iter()
             .map(|command_buffer| {
-                let in_flight =
-                    unsafe { device.create_fence(&fence_create_info, None) }.unwrap();
-                let image_available =
-                    unsafe { device.create_semaphore(&semaphore_create_info, None) }.unwrap();
-                let render_finished =
-                    unsafe { device.create_semaphore(&semaphore_create_info, None) }.unwrap();
-                super::SyncObject {
-                    in_flight,
-                    image_available,
-                    render_finished,
-                    command_buffer,
-                }
+                let in_flight = super::Owned::new(
+                    device,
+                    unsafe { device.create_fence(&fence_create_info, None) }.result()?,
+                );
+                let image_available = super::Owned::new(
+                    device,
+                    unsafe { device.create_semaphore(&semaphore_create_info, None) }.result()?,
+                );
+                let render_finished = super::Owned::new(
+                    device,
+                    unsafe { device.create_semaphore(&semaphore_create_info, None) }.result()?,
+                );
+                Ok((in_flight, image_available, render_finished, command_buffer))
             })
-            .collect();
+            .collect::<Result<Vec<_>, super::RenderError>>()?;
 
-        Self {
+        Ok(Self {
+            command_pool: command_pool.keep(),
+            syncs: syncs
+                .into_iter()
+                .map(
+                    |(in_flight, image_available, render_finished, command_buffer)| {
+                        super::SyncObject {
+                            in_flight: in_flight.keep(),
+                            image_available: image_available.keep(),
+                            render_finished: render_finished.keep(),
+                            command_buffer,
+                        }
+                    },
+                )
+                .collect(),
             instance,
-            command_pool,
-            syncs,
             current_frame: 0,
-        }
+        })
     }
 
-    pub fn render<Recorder: FnOnce(vk::CommandBuffer)>(
+    // `prepass` records work that has to happen before the surface's render pass begins, such as
+    // rendering shadow maps, and `recorder` records the render pass itself. Returns false if the
+    // surface has to be rebuilt.
+    pub fn render<Prepass: FnOnce(vk::CommandBuffer), Recorder: FnOnce(vk::CommandBuffer)>(
         &mut self,
         surface: &mut super::Surface,
+        prepass: Prepass,
         recorder: Recorder,
-    ) -> bool {
+    ) -> Result<bool, super::RenderError> {
         self.current_frame = (self.current_frame + 1) % self.syncs.len();
         let device = self.instance.device();
         let sync = &self.syncs[self.current_frame];
         let command_buffer_begin_info = vk::CommandBufferBeginInfoBuilder::new()
             .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
 
-        unsafe { device.wait_for_fences(&[sync.in_flight], true, u64::MAX) }.unwrap();
+        unsafe { device.wait_for_fences(&[sync.in_flight], true, u64::MAX) }.result()?;
 
         surface.borrow_image(sync, |render_pass_begin_info| {
             unsafe {
                 device
                     .begin_command_buffer(sync.command_buffer, &command_buffer_begin_info)
-                    .unwrap();
+                    .result()?;
+                prepass(sync.command_buffer);
                 device.cmd_begin_render_pass(
                     sync.command_buffer,
                     &render_pass_begin_info,
@@ -84,10 +104,10 @@ impl Renderer {
                 );
                 recorder(sync.command_buffer);
                 device.cmd_end_render_pass(sync.command_buffer);
-                device.end_command_buffer(sync.command_buffer).unwrap()
+                device.end_command_buffer(sync.command_buffer).result()?
             }
 
-            unsafe { device.reset_fences(&[sync.in_flight]) }.unwrap();
+            unsafe { device.reset_fences(&[sync.in_flight]) }.result()?;
 
             let submit_info = vk::SubmitInfoBuilder::new()
                 .wait_semaphores(std::slice::from_ref(&sync.image_available))
@@ -101,9 +121,50 @@ impl Renderer {
                     Some(sync.in_flight),
                 )
             }
-            .unwrap();
+            .result()?;
+            Ok(())
         })
     }
+
+    // Renders a single frame into `offscreen` and blocks until its pixels have been read back.
+    pub fn render_offscreen<Prepass: FnOnce(vk::CommandBuffer), Recorder: FnOnce(vk::CommandBuffer)>(
+        &mut self,
+        offscreen: &super::Offscreen,
+        prepass: Prepass,
+        recorder: Recorder,
+    ) -> Result<crate::render::Image, super::RenderError> {
+        self.current_frame = (self.current_frame + 1) % self.syncs.len();
+        let device = self.instance.device();
+        let sync = &self.syncs[self.current_frame];
+        let command_buffer_begin_info = vk::CommandBufferBeginInfoBuilder::new()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+        unsafe { device.wait_for_fences(&[sync.in_flight], true, u64::MAX) }.result()?;
+        unsafe { device.reset_fences(&[sync.in_flight]) }.result()?;
+
+        unsafe {
+            device
+                .begin_command_buffer(sync.command_buffer, &command_buffer_begin_info)
+                .result()?
+        }
+        prepass(sync.command_buffer);
+        offscreen.record(sync.command_buffer, recorder);
+        unsafe { device.end_command_buffer(sync.command_buffer) }.result()?;
+
+        let submit_info =
+            vk::SubmitInfoBuilder::new().command_buffers(std::slice::from_ref(&sync.command_buffer));
+        unsafe {
+            device.queue_submit(
+                self.instance.graphics().queue,
+                &[submit_info],
+                Some(sync.in_flight),
+            )
+        }
+        .result()?;
+        unsafe { device.wait_for_fences(&[sync.in_flight], true, u64::MAX) }.result()?;
+
+        Ok(offscreen.read())
+    }
 }
 
 impl Drop for Renderer {
@@ -0,0 +1,309 @@
+use erupt::vk;
+
+// A depth-only image rendered from the sun's point of view, sampled by `voxel.frag` to tell which
+// fragments the sun can't see.
+pub struct ShadowMap {
+    instance: std::rc::Rc<super::Instance>,
+    render_pass: vk::RenderPass,
+    image: super::Image,
+    framebuffer: vk::Framebuffer,
+    sampler: vk::Sampler,
+    layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+}
+
+impl ShadowMap {
+    const SIZE: u32 = 2048;
+    const FORMAT: vk::Format = vk::Format::D32_SFLOAT;
+    const PUSH_CONSTANTS_SIZE: u32 = 64; // mat4 light view-projection
+
+    // The pipeline uses `descriptor_set_layout` for the chunk table, like `voxel.vert`.
+    pub fn new(
+        instance: std::rc::Rc<super::Instance>,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<Self, super::RenderError> {
+        let device = instance.device();
+        let extent = vk::Extent2D {
+            width: Self::SIZE,
+            height: Self::SIZE,
+        };
+
+        // Create Render Pass
+
+        let attachments = [vk::AttachmentDescriptionBuilder::new()
+            .format(Self::FORMAT)
+            .samples(vk::SampleCountFlagBits::_1)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL)];
+        let depth_stencil_attachment = vk::AttachmentReferenceBuilder::new()
+            .attachment(0)
+            .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
+        let subpass = vk::SubpassDescriptionBuilder::new()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .depth_stencil_attachment(&depth_stencil_attachment);
+        let dependencies = [
+            // The previous frame must be done sampling the map before it's overwritten.
+            vk::SubpassDependencyBuilder::new()
+                .src_subpass(vk::SUBPASS_EXTERNAL)
+                .dst_subpass(0)
+                .src_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+                .src_access_mask(vk::AccessFlags::SHADER_READ)
+                .dst_stage_mask(
+                    vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                        | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                )
+                .dst_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE),
+            vk::SubpassDependencyBuilder::new()
+                .src_subpass(0)
+                .dst_subpass(vk::SUBPASS_EXTERNAL)
+                .src_stage_mask(vk::PipelineStageFlags::LATE_FRAGMENT_TESTS)
+                .src_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ),
+        ];
+        let render_pass_create_info = vk::RenderPassCreateInfoBuilder::new()
+            .attachments(&attachments)
+            .subpasses(std::slice::from_ref(&subpass))
+            .dependencies(&dependencies);
+        let render_pass = super::Owned::new(
+            device,
+            unsafe { device.create_render_pass(&render_pass_create_info, None) }.result()?,
+        );
+
+        // Create Depth Image Resources
+
+        let image = super::Image::new_2d(
+            instance.clone(),
+            Self::FORMAT,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+            vk::ImageAspectFlags::DEPTH,
+            extent,
+        )?;
+        let attachments = [image.view()];
+        let framebuffer_create_info = vk::FramebufferCreateInfoBuilder::new()
+            .render_pass(*render_pass)
+            .attachments(&attachments)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(1);
+        let framebuffer = super::Owned::new(
+            device,
+            unsafe { device.create_framebuffer(&framebuffer_create_info, None) }.result()?,
+        );
+
+        // Everything outside the map is lit.
+        let sampler_create_info = vk::SamplerCreateInfoBuilder::new()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+            .border_color(vk::BorderColor::FLOAT_OPAQUE_WHITE)
+            .compare_enable(true)
+            .compare_op(vk::CompareOp::LESS_OR_EQUAL)
+            .min_lod(0.)
+            .max_lod(0.);
+        let sampler = super::Owned::new(
+            device,
+            unsafe { device.create_sampler(&sampler_create_info, None) }.result()?,
+        );
+
+        let (layout, pipeline) =
+            Self::create_pipeline(&instance, descriptor_set_layout, *render_pass, extent)?;
+
+        Ok(Self {
+            render_pass: render_pass.keep(),
+            framebuffer: framebuffer.keep(),
+            sampler: sampler.keep(),
+            instance,
+            image,
+            layout,
+            pipeline,
+        })
+    }
+
+    fn create_pipeline(
+        instance: &super::Instance,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        render_pass: vk::RenderPass,
+        extent: vk::Extent2D,
+    ) -> Result<(vk::PipelineLayout, vk::Pipeline), super::RenderError> {
+        let device = instance.device();
+
+        let vert_shader_module =
+            instance.create_shader_module("shadow.vert", include_shader!("shadow.vert"))?;
+
+        let entry_point = std::ffi::CString::new("main").unwrap();
+
+        let stages = [vk::PipelineShaderStageCreateInfoBuilder::new()
+            .stage(vk::ShaderStageFlagBits::VERTEX)
+            .module(*vert_shader_module)
+            .name(&entry_point)];
+
+        let input_binding_descriptions = [vk::VertexInputBindingDescriptionBuilder::new()
+            .binding(0)
+            .stride(std::mem::size_of::<crate::voxel::Face>() as u32)
+            .input_rate(vk::VertexInputRate::INSTANCE)];
+
+        let input_attribute_descriptions = [vk::VertexInputAttributeDescriptionBuilder::new()
+            .binding(0)
+            .location(0)
+            .format(vk::Format::R32_UINT)
+            .offset(0)];
+
+        let vertex_input = vk::PipelineVertexInputStateCreateInfoBuilder::new()
+            .vertex_binding_descriptions(&input_binding_descriptions)
+            .vertex_attribute_descriptions(&input_attribute_descriptions);
+
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfoBuilder::new()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+            .primitive_restart_enable(false);
+
+        let viewports = [vk::ViewportBuilder::new()
+            .x(0.0)
+            .y(0.0)
+            .width(extent.width as f32)
+            .height(extent.height as f32)
+            .min_depth(0.0)
+            .max_depth(1.0)];
+        let scissors = [vk::Rect2DBuilder::new()
+            .offset(vk::Offset2D { x: 0, y: 0 })
+            .extent(extent)];
+        let viewport = vk::PipelineViewportStateCreateInfoBuilder::new()
+            .viewports(&viewports)
+            .scissors(&scissors);
+
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfoBuilder::new()
+            .depth_test_enable(true)
+            .depth_write_enable(true)
+            .depth_compare_op(vk::CompareOp::LESS);
+
+        // Both sides are drawn, since faces turned away from the sun still cast shadows, and the
+        // depth bias keeps lit faces from shadowing themselves.
+        let rasterization = vk::PipelineRasterizationStateCreateInfoBuilder::new()
+            .depth_clamp_enable(false)
+            .rasterizer_discard_enable(false)
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .cull_mode(vk::CullModeFlags::NONE)
+            .front_face(vk::FrontFace::CLOCKWISE)
+            .depth_bias_enable(true)
+            .depth_bias_constant_factor(1.25)
+            .depth_bias_slope_factor(1.75);
+        let multisample = vk::PipelineMultisampleStateCreateInfoBuilder::new()
+            .sample_shading_enable(false)
+            .rasterization_samples(vk::SampleCountFlagBits::_1);
+
+        let color_blend = vk::PipelineColorBlendStateCreateInfoBuilder::new()
+            .logic_op_enable(false)
+            .logic_op(vk::LogicOp::COPY);
+
+        let push_constant_ranges = [vk::PushConstantRangeBuilder::new()
+            .offset(0)
+            .size(Self::PUSH_CONSTANTS_SIZE)
+            .stage_flags(vk::ShaderStageFlags::VERTEX)];
+
+        let layout_info = vk::PipelineLayoutCreateInfoBuilder::new()
+            .set_layouts(std::slice::from_ref(&descriptor_set_layout))
+            .push_constant_ranges(&push_constant_ranges);
+
+        let layout = super::Owned::new(
+            device,
+            unsafe { device.create_pipeline_layout(&layout_info, None) }.result()?,
+        );
+
+        let pipeline_create_info = vk::GraphicsPipelineCreateInfoBuilder::new()
+            .stages(&stages)
+            .vertex_input_state(&vertex_input)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport)
+            .depth_stencil_state(&depth_stencil)
+            .rasterization_state(&rasterization)
+            .multisample_state(&multisample)
+            .color_blend_state(&color_blend)
+            .layout(*layout)
+            .render_pass(render_pass)
+            .subpass(0);
+
+        let pipeline =
+            unsafe { device.create_graphics_pipelines(None, &[pipeline_create_info], None) }
+                .result()?[0];
+
+        Ok((layout.keep(), pipeline))
+    }
+
+    // Renders the map. `draw` is called inside the render pass with the pipeline bound, and should
+    // bind the descriptor set with the chunk table using the given pipeline layout.
+    pub fn record<F: FnOnce(vk::PipelineLayout)>(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        matrix: &ultraviolet::Mat4,
+        draw: F,
+    ) {
+        let device = self.instance.device();
+        let clear_values = [vk::ClearValue {
+            depth_stencil: vk::ClearDepthStencilValue {
+                depth: 1.0,
+                stencil: 0,
+            },
+        }];
+        let render_pass_begin_info = vk::RenderPassBeginInfoBuilder::new()
+            .render_pass(self.render_pass)
+            .framebuffer(self.framebuffer)
+            .render_area(vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: vk::Extent2D {
+                    width: Self::SIZE,
+                    height: Self::SIZE,
+                },
+            })
+            .clear_values(&clear_values);
+        unsafe {
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &render_pass_begin_info,
+                vk::SubpassContents::INLINE,
+            );
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline,
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.layout,
+                vk::ShaderStageFlags::VERTEX,
+                0,
+                64,
+                matrix.as_ptr().cast(),
+            );
+        }
+        draw(self.layout);
+        unsafe { device.cmd_end_render_pass(command_buffer) }
+    }
+
+    pub fn image_info(&self) -> vk::DescriptorImageInfoBuilder<'static> {
+        vk::DescriptorImageInfoBuilder::new()
+            .sampler(self.sampler)
+            .image_view(self.image.view())
+            .image_layout(vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL)
+    }
+}
+
+impl Drop for ShadowMap {
+    fn drop(&mut self) {
+        let device = self.instance.device();
+        unsafe {
+            device.destroy_pipeline_layout(Some(self.layout), None);
+            device.destroy_pipeline(Some(self.pipeline), None);
+            device.destroy_sampler(Some(self.sampler), None);
+            device.destroy_framebuffer(Some(self.framebuffer), None);
+            device.destroy_render_pass(Some(self.render_pass), None)
+        }
+    }
+}
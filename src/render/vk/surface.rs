@@ -4,18 +4,39 @@ pub struct Surface {
     instance: std::rc::Rc<super::Instance>,
     render_pass: vk::RenderPass,
     swapchain: vk::SwapchainKHR,
-    depth_image: vk::Image,
-    depth_image_memory: vk::DeviceMemory,
-    depth_image_view: vk::ImageView,
+    #[allow(dead_code)]
+    depth_image: super::Image,
     swapchain_images: Vec<SwapchainImage>,
     extent: vk::Extent2D,
+    present_mode: PresentMode,
+    out_of_date: bool,
 }
 
 impl Surface {
-    pub fn new(instance: std::rc::Rc<super::Instance>, window: &crate::window::Window) -> Self {
-        let instance = instance.clone();
+    pub fn new(
+        instance: std::rc::Rc<super::Instance>,
+        window: &crate::window::Window,
+        present_mode: PresentMode,
+    ) -> Result<Self, super::RenderError> {
+        Self::create(instance, window, present_mode, vk::SwapchainKHR::null())
+    }
+
+    // `old_swapchain` is handed over to the new swapchain, and must be destroyed afterwards.
+    fn create(
+        instance: std::rc::Rc<super::Instance>,
+        window: &crate::window::Window,
+        present_mode: PresentMode,
+        old_swapchain: vk::SwapchainKHR,
+    ) -> Result<Self, super::RenderError> {
         let device = instance.device();
-        let surface_info = instance.surface_info(window.size().into());
+        let surface_info = instance.surface_info(window.size().into(), present_mode)?;
+        if surface_info.present_mode != present_mode.candidates()[0] {
+            log::warn!(
+                "{:?} presentation is not supported, using {:?}",
+                present_mode,
+                surface_info.present_mode
+            )
+        }
 
         // Create Render Pass
 
@@ -69,8 +90,10 @@ impl Surface {
             .attachments(&attachments)
             .subpasses(std::slice::from_ref(&subpass))
             .dependencies(std::slice::from_ref(&dependency));
-        let render_pass =
-            unsafe { device.create_render_pass(&render_pass_create_info, None) }.unwrap();
+        let render_pass = super::Owned::new(
+            device,
+            unsafe { device.create_render_pass(&render_pass_create_info, None) }.result()?,
+        );
 
         // Create Swapchain
 
@@ -97,55 +120,26 @@ impl Surface {
             .pre_transform(surface_info.surface_caps.current_transform)
             .composite_alpha(vk::CompositeAlphaFlagBitsKHR::OPAQUE_KHR)
             .present_mode(surface_info.present_mode)
-            .clipped(true);
-        let swapchain =
-            unsafe { device.create_swapchain_khr(&swapchain_create_info, None) }.unwrap();
+            .clipped(true)
+            .old_swapchain(old_swapchain);
+        let swapchain = super::Owned::new(
+            device,
+            unsafe { device.create_swapchain_khr(&swapchain_create_info, None) }.result()?,
+        );
 
         // Create Depth Image Resources
 
-        let image_create_info = vk::ImageCreateInfoBuilder::new()
-            .image_type(vk::ImageType::_2D)
-            .extent(vk::Extent3D {
-                width: surface_info.extent.width,
-                height: surface_info.extent.height,
-                depth: 1,
-            })
-            .mip_levels(1)
-            .array_layers(1)
-            .format(vk::Format::D32_SFLOAT)
-            .tiling(vk::ImageTiling::OPTIMAL)
-            .initial_layout(vk::ImageLayout::UNDEFINED)
-            .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)
-            .samples(vk::SampleCountFlagBits::_1)
-            .sharing_mode(vk::SharingMode::EXCLUSIVE);
-        let depth_image = unsafe { device.create_image(&image_create_info, None) }.unwrap();
-        let memory_requirements =
-            unsafe { device.get_image_memory_requirements(depth_image) };
-        let memory_type_index = instance
-            .get_memory_type_index(vk::MemoryPropertyFlags::DEVICE_LOCAL, memory_requirements);
-        let memory_allocate_info = vk::MemoryAllocateInfoBuilder::new()
-            .allocation_size(memory_requirements.size)
-            .memory_type_index(memory_type_index);
-        let depth_image_memory =
-            unsafe { device.allocate_memory(&memory_allocate_info, None) }.unwrap();
-        unsafe { device.bind_image_memory(depth_image, depth_image_memory, 0) }.unwrap();
-        let image_view_create_info = vk::ImageViewCreateInfoBuilder::new()
-            .image(depth_image)
-            .view_type(vk::ImageViewType::_2D)
-            .format(vk::Format::D32_SFLOAT)
-            .subresource_range(vk::ImageSubresourceRange {
-                aspect_mask: vk::ImageAspectFlags::DEPTH,
-                base_mip_level: 0,
-                level_count: 1,
-                base_array_layer: 0,
-                layer_count: 1,
-            });
-        let depth_image_view =
-            unsafe { device.create_image_view(&image_view_create_info, None) }.unwrap();
+        let depth_image = super::Image::new_2d(
+            instance.clone(),
+            vk::Format::D32_SFLOAT,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            vk::ImageAspectFlags::DEPTH,
+            surface_info.extent,
+        )?;
 
         // Create Swapchain Image Resources and Framebuffers
 
-        let images = unsafe { device.get_swapchain_images_khr(swapchain, None) }.unwrap();
+        let images = unsafe { device.get_swapchain_images_khr(*swapchain, None) }.result()?;
         let swapchain_images = images
             .into_iter()
             .map(|image| {
@@ -160,48 +154,62 @@ impl Surface {
                         base_array_layer: 0,
                         layer_count: 1,
                     });
-                let view =
-                    unsafe { device.create_image_view(&view_create_info, None) }.unwrap();
-                let attachments = [view, depth_image_view];
+                let view = super::Owned::new(
+                    device,
+                    unsafe { device.create_image_view(&view_create_info, None) }.result()?,
+                );
+                let attachments = [*view, depth_image.view()];
                 let framebuffer_create_info = vk::FramebufferCreateInfoBuilder::new()
-                    .render_pass(render_pass)
+                    .render_pass(*render_pass)
                     .attachments(&attachments)
                     .width(surface_info.extent.width)
                     .height(surface_info.extent.height)
                     .layers(1);
-                let framebuffer =
+                let framebuffer = super::Owned::new(
+                    device,
                     unsafe { device.create_framebuffer(&framebuffer_create_info, None) }
-                        .unwrap();
-                SwapchainImage {
-                    view,
-                    framebuffer,
-                    fence: vk::Fence::null(),
-                }
+                        .result()?,
+                );
+                Ok((view, framebuffer))
             })
-            .collect();
+            .collect::<Result<Vec<_>, super::RenderError>>()?;
 
-        Self {
+        Ok(Self {
+            render_pass: render_pass.keep(),
+            swapchain: swapchain.keep(),
+            swapchain_images: swapchain_images
+                .into_iter()
+                .map(|(view, framebuffer)| SwapchainImage {
+                    view: view.keep(),
+                    framebuffer: framebuffer.keep(),
+                    fence: vk::Fence::null(),
+                })
+                .collect(),
             instance,
-            render_pass,
-            swapchain,
             depth_image,
-            depth_image_memory,
-            depth_image_view,
-            swapchain_images,
             extent: surface_info.extent,
-        }
+            present_mode,
+            out_of_date: false,
+        })
     }
 
     pub fn instance(&self) -> std::rc::Rc<super::Instance> {
         self.instance.clone()
     }
 
-    pub(super) fn borrow_image<F: FnOnce(vk::RenderPassBeginInfoBuilder)>(
+    // Runs `f` with the next swapchain image and presents it. Returns false when the swapchain is
+    // out of date and has to be rebuilt, in which case `f` may not have run.
+    pub(super) fn borrow_image<
+        F: FnOnce(vk::RenderPassBeginInfoBuilder) -> Result<(), super::RenderError>,
+    >(
         &mut self,
         sync: &super::SyncObject,
         f: F,
-    ) -> bool {
+    ) -> Result<bool, super::RenderError> {
         let device = self.instance.device();
+        if self.out_of_date {
+            return Ok(false);
+        }
 
         // Acquire Image from Swapchain
 
@@ -217,31 +225,18 @@ impl Surface {
         {
             Ok(x) => x as usize,
             Err(vk::Result::SUBOPTIMAL_KHR) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
-                return false;
+                return Ok(false);
             }
-            Err(e) => panic!("{}", e),
+            Err(e) => return Err(e.into()),
         };
         if !self.swapchain_images[index].fence.is_null() {
             unsafe { device.wait_for_fences(&[self.swapchain_images[index].fence], true, u64::MAX) }
-                .unwrap()
+                .result()?
         };
         self.swapchain_images[index].fence = sync.in_flight;
 
         // Run Callback
 
-        const CLEAR_VALUES: [vk::ClearValue; 2] = [
-            vk::ClearValue {
-                color: vk::ClearColorValue {
-                    float32: [0.0, 0.0, 0.0, 0.0],
-                },
-            },
-            vk::ClearValue {
-                depth_stencil: vk::ClearDepthStencilValue {
-                    depth: 1.0,
-                    stencil: 0,
-                },
-            },
-        ];
         let render_pass_begin_info = vk::RenderPassBeginInfoBuilder::new()
             .render_pass(self.render_pass)
             .framebuffer(self.swapchain_images[index].framebuffer)
@@ -249,8 +244,8 @@ impl Surface {
                 offset: vk::Offset2D { x: 0, y: 0 },
                 extent: self.extent,
             })
-            .clear_values(&CLEAR_VALUES);
-        f(render_pass_begin_info);
+            .clear_values(&super::CLEAR_VALUES);
+        f(render_pass_begin_info)?;
 
         // Present Image
 
@@ -264,27 +259,30 @@ impl Surface {
         {
             Ok(_) => (),
             Err(vk::Result::SUBOPTIMAL_KHR) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
-                return false;
+                return Ok(false);
             }
-            Err(e) => panic!("{}", e),
+            Err(e) => return Err(e.into()),
         };
 
-        true
+        Ok(true)
     }
 
-    pub(super) fn render_info(&self) -> super::RenderInfo {
-        super::RenderInfo {
-            render_pass: self.render_pass,
-            extent: self.extent,
-        }
+    // Recreates the swapchain for the window's current size and the present mode. The device must
+    // be idle. If this fails, the old swapchain is kept, though it may be unusable.
+    pub fn rebuild(&mut self, window: &crate::window::Window) -> Result<(), super::RenderError> {
+        *self = Self::create(
+            self.instance.clone(),
+            window,
+            self.present_mode,
+            self.swapchain,
+        )?;
+        Ok(())
     }
 
-    pub fn rebuild(&mut self, window: &crate::window::Window) {
-        let instance = self.instance.clone();
-        unsafe {
-            std::mem::drop(std::ptr::read(self));
-            std::ptr::write(self, Self::new(instance, window))
-        }
+    // Takes effect once the swapchain is rebuilt, which the next `Renderer::render` asks for.
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        self.present_mode = present_mode;
+        self.out_of_date = true
     }
 
     pub fn aspect_ratio(&self) -> f32 {
@@ -292,6 +290,19 @@ impl Surface {
     }
 }
 
+impl super::Target for Surface {
+    fn instance(&self) -> std::rc::Rc<super::Instance> {
+        self.instance.clone()
+    }
+
+    fn render_info(&self) -> super::RenderInfo {
+        super::RenderInfo {
+            render_pass: self.render_pass,
+            extent: self.extent,
+        }
+    }
+}
+
 impl Drop for Surface {
     fn drop(&mut self) {
         let device = self.instance.device();
@@ -300,9 +311,6 @@ impl Drop for Surface {
             device.destroy_image_view(Some(image.view), None)
         });
         unsafe {
-            device.destroy_image_view(Some(self.depth_image_view), None);
-            device.free_memory(Some(self.depth_image_memory), None);
-            device.destroy_image(Some(self.depth_image), None);
             device.destroy_swapchain_khr(Some(self.swapchain), None);
             device.destroy_render_pass(Some(self.render_pass), None)
         }
@@ -315,3 +323,67 @@ struct SwapchainImage {
     framebuffer: vk::Framebuffer,
     fence: vk::Fence,
 }
+
+// How finished frames are shown. `Fifo` waits for the display's vertical blank (vsync on), and
+// `FifoRelaxed` does too unless a frame is late, in which case it tears rather than waiting another
+// refresh. `Mailbox` doesn't block, but always shows the newest frame at the next vertical blank.
+// `Immediate` shows frames as soon as they are done and may tear (vsync off). Modes the device
+// doesn't support fall back to the closest one it does, ending with `Fifo`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PresentMode {
+    Fifo,
+    FifoRelaxed,
+    Mailbox,
+    Immediate,
+}
+
+impl PresentMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Fifo => Self::FifoRelaxed,
+            Self::FifoRelaxed => Self::Mailbox,
+            Self::Mailbox => Self::Immediate,
+            Self::Immediate => Self::Fifo,
+        }
+    }
+
+    // The Vulkan modes to try, best first. Every device supports FIFO.
+    pub(super) fn candidates(self) -> &'static [vk::PresentModeKHR] {
+        match self {
+            Self::Fifo => &[vk::PresentModeKHR::FIFO_KHR],
+            Self::FifoRelaxed => &[
+                vk::PresentModeKHR::FIFO_RELAXED_KHR,
+                vk::PresentModeKHR::FIFO_KHR,
+            ],
+            Self::Mailbox => &[
+                vk::PresentModeKHR::MAILBOX_KHR,
+                vk::PresentModeKHR::FIFO_KHR,
+            ],
+            Self::Immediate => &[
+                vk::PresentModeKHR::IMMEDIATE_KHR,
+                vk::PresentModeKHR::MAILBOX_KHR,
+                vk::PresentModeKHR::FIFO_KHR,
+            ],
+        }
+    }
+}
+
+impl Default for PresentMode {
+    fn default() -> Self {
+        Self::Mailbox
+    }
+}
+
+impl std::str::FromStr for PresentMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vsync" | "fifo" => Ok(Self::Fifo),
+            "relaxed" => Ok(Self::FifoRelaxed),
+            "mailbox" => Ok(Self::Mailbox),
+            "immediate" => Ok(Self::Immediate),
+            _ => Err(format!("unknown present mode `{}`", s)),
+        }
+    }
+}
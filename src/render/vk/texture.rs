@@ -0,0 +1,226 @@
+use erupt::vk;
+
+// A sampled 2D array texture with a full mip chain, one layer per image.
+pub struct TextureArray {
+    image: super::Image,
+}
+
+impl TextureArray {
+    const FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+
+    // All images must have the same size. Their pixels are treated as sRGB.
+    pub fn new(
+        instance: std::rc::Rc<super::Instance>,
+        images: &[crate::render::Image],
+    ) -> Result<Self, super::RenderError> {
+        let device = instance.device();
+        let (width, height) = (images[0].width(), images[0].height());
+        assert!(images
+            .iter()
+            .all(|image| (image.width(), image.height()) == (width, height)));
+        let layers = images.len() as u32;
+        let mip_levels = 32 - width.max(height).leading_zeros();
+
+        // Create Staging Buffer
+
+        let layer_size = width as u64 * height as u64 * 4;
+        let staging_buffer = super::Buffer::new(
+            instance.clone(),
+            layer_size * layers as u64,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+        let memory = staging_buffer.mapped().unwrap();
+        for (index, image) in images.iter().enumerate() {
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    image.pixels().as_ptr(),
+                    memory.add(index * layer_size as usize),
+                    layer_size as usize,
+                )
+            }
+        }
+
+        // Create Image Resources
+
+        let image_create_info = vk::ImageCreateInfoBuilder::new()
+            .image_type(vk::ImageType::_2D)
+            .extent(vk::Extent3D {
+                width,
+                height,
+                depth: 1,
+            })
+            .mip_levels(mip_levels)
+            .array_layers(layers)
+            .format(Self::FORMAT)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(
+                vk::ImageUsageFlags::TRANSFER_SRC
+                    | vk::ImageUsageFlags::TRANSFER_DST
+                    | vk::ImageUsageFlags::SAMPLED,
+            )
+            .samples(vk::SampleCountFlagBits::_1)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        let texture_image = super::Image::new(
+            instance.clone(),
+            &image_create_info,
+            vk::ImageViewType::_2D_ARRAY,
+            vk::ImageAspectFlags::COLOR,
+        )?;
+        let image = texture_image.handle();
+
+        // Upload Level 0 & Generate Mipmaps
+
+        let barrier = |level: u32,
+                       old_layout: vk::ImageLayout,
+                       new_layout: vk::ImageLayout,
+                       src_access_mask: vk::AccessFlags,
+                       dst_access_mask: vk::AccessFlags| {
+            vk::ImageMemoryBarrierBuilder::new()
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: level,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: layers,
+                })
+                .src_access_mask(src_access_mask)
+                .dst_access_mask(dst_access_mask)
+        };
+        let subresource = |level: u32| vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: level,
+            base_array_layer: 0,
+            layer_count: layers,
+        };
+        let level_extent = |level: u32| vk::Offset3D {
+            x: (width >> level).max(1) as i32,
+            y: (height >> level).max(1) as i32,
+            z: 1,
+        };
+
+        instance.submit_once(|command_buffer| unsafe {
+            let all_levels = vk::ImageMemoryBarrierBuilder::new()
+                .old_layout(vk::ImageLayout::UNDEFINED)
+                .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: mip_levels,
+                    base_array_layer: 0,
+                    layer_count: layers,
+                })
+                .src_access_mask(vk::AccessFlags::empty())
+                .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE);
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                None,
+                &[],
+                &[],
+                &[all_levels],
+            );
+
+            let region = vk::BufferImageCopyBuilder::new()
+                .buffer_offset(0)
+                .buffer_row_length(0)
+                .buffer_image_height(0)
+                .image_subresource(subresource(0))
+                .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+                .image_extent(vk::Extent3D {
+                    width,
+                    height,
+                    depth: 1,
+                });
+            device.cmd_copy_buffer_to_image(
+                command_buffer,
+                staging_buffer.handle(),
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[region],
+            );
+
+            // Each level is blitted from the one above it, which is then done being written to.
+            for level in 1..mip_levels {
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::TRANSFER,
+                    None,
+                    &[],
+                    &[],
+                    &[barrier(
+                        level - 1,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        vk::AccessFlags::TRANSFER_WRITE,
+                        vk::AccessFlags::TRANSFER_READ,
+                    )],
+                );
+                let blit = vk::ImageBlitBuilder::new()
+                    .src_subresource(subresource(level - 1))
+                    .src_offsets([vk::Offset3D { x: 0, y: 0, z: 0 }, level_extent(level - 1)])
+                    .dst_subresource(subresource(level))
+                    .dst_offsets([vk::Offset3D { x: 0, y: 0, z: 0 }, level_extent(level)]);
+                device.cmd_blit_image(
+                    command_buffer,
+                    image,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[blit],
+                    vk::Filter::LINEAR,
+                );
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::FRAGMENT_SHADER,
+                    None,
+                    &[],
+                    &[],
+                    &[barrier(
+                        level - 1,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                        vk::AccessFlags::TRANSFER_READ,
+                        vk::AccessFlags::SHADER_READ,
+                    )],
+                );
+            }
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                None,
+                &[],
+                &[],
+                &[barrier(
+                    mip_levels - 1,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::AccessFlags::SHADER_READ,
+                )],
+            );
+        })?;
+
+        Ok(Self {
+            image: texture_image,
+        })
+    }
+
+    pub fn view(&self) -> vk::ImageView {
+        self.image.view()
+    }
+}
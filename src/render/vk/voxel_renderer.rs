@@ -2,56 +2,306 @@ use erupt::vk;
 
 pub struct VoxelRenderer {
     instance: std::rc::Rc<super::Instance>,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    sampler: vk::Sampler,
+    textures: super::TextureArray,
+    shadow_map: super::ShadowMap,
+    cull_pipeline: super::CullPipeline,
+    culling: Culling,
+    frames: Vec<FrameResources>, // one per frame in flight
+    lighting: Lighting,
+    chunk_table: Vec<ChunkInfo>,
+    // A command for every chunk in the chunk table, for the shadow pass, followed by the commands
+    // for the main pass if chunks are culled on the CPU.
+    commands: Vec<vk::DrawIndirectCommand>,
+    view_matrix: ultraviolet::Mat4,
+    counts: ChunkCounts,
     layout: vk::PipelineLayout,
     pipeline: vk::Pipeline,
-    mesh: VoxelMesh,
+    faces: super::FacePool,
+    meshes: std::collections::HashMap<crate::voxel::ChunkCoord, super::FaceRange>,
+    retired: Vec<(usize, super::FaceRange)>, // (frame retired in, faces)
+    frame: usize,
 }
 
 impl VoxelRenderer {
-    pub fn new(surface: &super::Surface, faces: &[u32]) -> Self {
-        let instance = surface.instance();
+    const PUSH_CONSTANTS_SIZE: u32 = 64; // mat4 view-projection
+    const INITIAL_CHUNK_CAPACITY: usize = 256;
+    const INITIAL_STAGING_CAPACITY: usize = 1 << 14; // faces
+
+    // Starts out with a single plain white texture layer, until `set_textures` is called, and with
+    // chunks culled on the CPU.
+    pub fn new<T: super::Target>(target: &T) -> Result<Self, super::RenderError> {
+        let instance = target.instance();
+        let device = instance.device();
+        let frames = super::Renderer::MAX_FRAMES_IN_FLIGHT;
+
+        // Create Descriptor Sets
+
+        let bindings = [
+            vk::DescriptorSetLayoutBindingBuilder::new()
+                .binding(0)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::FRAGMENT),
+            vk::DescriptorSetLayoutBindingBuilder::new()
+                .binding(1)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::FRAGMENT),
+            vk::DescriptorSetLayoutBindingBuilder::new()
+                .binding(2)
+                .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT),
+            vk::DescriptorSetLayoutBindingBuilder::new()
+                .binding(3)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::COMPUTE),
+            vk::DescriptorSetLayoutBindingBuilder::new()
+                .binding(4)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE),
+            vk::DescriptorSetLayoutBindingBuilder::new()
+                .binding(5)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE),
+        ];
+        let descriptor_set_layout_create_info =
+            vk::DescriptorSetLayoutCreateInfoBuilder::new().bindings(&bindings);
+        let descriptor_set_layout = super::Owned::new(
+            device,
+            unsafe {
+                device.create_descriptor_set_layout(&descriptor_set_layout_create_info, None)
+            }
+            .result()?,
+        );
+
+        let pool_sizes = [
+            vk::DescriptorPoolSizeBuilder::new()
+                ._type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(2 * frames as u32),
+            vk::DescriptorPoolSizeBuilder::new()
+                ._type(vk::DescriptorType::UNIFORM_BUFFER)
+                .descriptor_count(frames as u32),
+            vk::DescriptorPoolSizeBuilder::new()
+                ._type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(3 * frames as u32),
+        ];
+        let descriptor_pool_create_info = vk::DescriptorPoolCreateInfoBuilder::new()
+            .pool_sizes(&pool_sizes)
+            .max_sets(frames as u32);
+        let descriptor_pool = super::Owned::new(
+            device,
+            unsafe { device.create_descriptor_pool(&descriptor_pool_create_info, None) }
+                .result()?,
+        );
+
+        let set_layouts = vec![*descriptor_set_layout; frames];
+        let descriptor_set_allocate_info = vk::DescriptorSetAllocateInfoBuilder::new()
+            .descriptor_pool(*descriptor_pool)
+            .set_layouts(&set_layouts);
+        let descriptor_sets =
+            unsafe { device.allocate_descriptor_sets(&descriptor_set_allocate_info) }.result()?;
+
+        // Blocky up close, smooth in the distance.
+        let sampler_create_info = vk::SamplerCreateInfoBuilder::new()
+            .mag_filter(vk::Filter::NEAREST)
+            .min_filter(vk::Filter::LINEAR)
+            .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::REPEAT)
+            .address_mode_v(vk::SamplerAddressMode::REPEAT)
+            .address_mode_w(vk::SamplerAddressMode::REPEAT)
+            .min_lod(0.)
+            .max_lod(vk::LOD_CLAMP_NONE);
+        let sampler = super::Owned::new(
+            device,
+            unsafe { device.create_sampler(&sampler_create_info, None) }.result()?,
+        );
+
+        let white = crate::render::Image::from_rgba(1, 1, vec![0xff; 4]);
+        let textures = super::TextureArray::new(instance.clone(), &[white])?;
+
+        // Create Lighting & Culling Resources
+
+        let shadow_map = super::ShadowMap::new(instance.clone(), *descriptor_set_layout)?;
+        let cull_pipeline = super::CullPipeline::new(instance.clone(), *descriptor_set_layout)?;
+        let frames = descriptor_sets
+            .iter()
+            .map(|&descriptor_set| {
+                FrameResources::new(
+                    instance.clone(),
+                    descriptor_set,
+                    Self::INITIAL_CHUNK_CAPACITY,
+                    Self::INITIAL_STAGING_CAPACITY,
+                )
+            })
+            .collect::<Result<_, _>>()?;
+        let faces = super::FacePool::new(instance.clone())?;
+
+        let (layout, pipeline) =
+            Self::create_pipeline(&instance, *descriptor_set_layout, target.render_info())?;
+
+        let renderer = Self {
+            descriptor_set_layout: descriptor_set_layout.keep(),
+            descriptor_pool: descriptor_pool.keep(),
+            sampler: sampler.keep(),
+            instance,
+            faces,
+            textures,
+            shadow_map,
+            cull_pipeline,
+            culling: Culling::Cpu,
+            frames,
+            lighting: Lighting::new(
+                &crate::render::Sun::default(),
+                ultraviolet::Mat4::identity(),
+            ),
+            chunk_table: Vec::new(),
+            commands: Vec::new(),
+            view_matrix: ultraviolet::Mat4::identity(),
+            counts: ChunkCounts::default(),
+            layout,
+            pipeline,
+            meshes: std::collections::HashMap::new(),
+            retired: Vec::new(),
+            frame: 0,
+        };
+        renderer.write_descriptor_sets();
+        Ok(renderer)
+    }
+
+    // Replaces the texture array, with layer `i` of the array taken from `images[i]`. Layer 0
+    // should be plain white, for faces without a texture. Waits for the device to go idle, since
+    // frames in flight may still be sampling the old textures.
+    pub fn set_textures(
+        &mut self,
+        images: &[crate::render::Image],
+    ) -> Result<(), super::RenderError> {
+        self.instance.wait_idle()?;
+        self.textures = super::TextureArray::new(self.instance.clone(), images)?;
+        self.write_descriptor_sets();
+        Ok(())
+    }
+
+    pub fn culling(&self) -> Culling {
+        self.culling
+    }
+
+    // Culling on the GPU needs the `multiDrawIndirect` and `drawIndirectFirstInstance` features.
+    // Without them, this logs a warning and keeps culling on the CPU.
+    pub fn set_culling(&mut self, culling: Culling) {
+        if culling == Culling::Gpu && !self.instance.multi_draw_indirect() {
+            log::warn!("GPU culling isn't supported by this device, culling on the CPU instead");
+            return;
+        }
+        self.culling = culling
+    }
+
+    fn write_descriptor_sets(&self) {
+        let image_info = vk::DescriptorImageInfoBuilder::new()
+            .sampler(self.sampler)
+            .image_view(self.textures.view())
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+        let shadow_info = self.shadow_map.image_info();
+        for frame in &self.frames {
+            let buffer_info = |buffer| {
+                vk::DescriptorBufferInfoBuilder::new()
+                    .buffer(buffer)
+                    .offset(0)
+                    .range(vk::WHOLE_SIZE)
+            };
+            let buffer_infos = [
+                buffer_info(frame.lighting.buffer()),
+                buffer_info(frame.chunk_table.buffer()),
+                buffer_info(frame.commands.buffer()),
+                buffer_info(frame.counts.buffer()),
+            ];
+            let mut writes = vec![
+                vk::WriteDescriptorSetBuilder::new()
+                    .dst_set(frame.descriptor_set)
+                    .dst_binding(0)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(std::slice::from_ref(&image_info)),
+                vk::WriteDescriptorSetBuilder::new()
+                    .dst_set(frame.descriptor_set)
+                    .dst_binding(1)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(std::slice::from_ref(&shadow_info)),
+                vk::WriteDescriptorSetBuilder::new()
+                    .dst_set(frame.descriptor_set)
+                    .dst_binding(2)
+                    .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                    .buffer_info(std::slice::from_ref(&buffer_infos[0])),
+            ];
+            writes.extend((3..6).map(|binding| {
+                vk::WriteDescriptorSetBuilder::new()
+                    .dst_set(frame.descriptor_set)
+                    .dst_binding(binding)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                    .buffer_info(std::slice::from_ref(&buffer_infos[binding as usize - 2]))
+            }));
+            unsafe { self.instance.device().update_descriptor_sets(&writes, &[]) }
+        }
+    }
+
+    fn create_pipeline(
+        instance: &super::Instance,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        render_info: super::RenderInfo,
+    ) -> Result<(vk::PipelineLayout, vk::Pipeline), super::RenderError> {
         let device = instance.device();
-        let render_info = surface.render_info();
 
-        let vert_spv = include_shader!("voxel.vert");
-        let vert_code  = erupt::utils::decode_spv(vert_spv).unwrap();
-        let vert_shader_module_create_info =
-            vk::ShaderModuleCreateInfoBuilder::new().code(&vert_code);
         let vert_shader_module =
-            unsafe { device.create_shader_module(&vert_shader_module_create_info, None) }
-                .unwrap();
+            instance.create_shader_module("voxel.vert", include_shader!("voxel.vert"))?;
 
-        let frag_spv = include_shader!("voxel.frag");
-        let frag_code = erupt::utils::decode_spv(frag_spv).unwrap();
-        let frag_shader_module_create_info =
-            vk::ShaderModuleCreateInfoBuilder::new().code(&frag_code);
         let frag_shader_module =
-            unsafe { device.create_shader_module(&frag_shader_module_create_info, None) }
-                .unwrap();
+            instance.create_shader_module("voxel.frag", include_shader!("voxel.frag"))?;
 
         let entry_point = std::ffi::CString::new("main").unwrap();
 
         let stages = [
             vk::PipelineShaderStageCreateInfoBuilder::new()
                 .stage(vk::ShaderStageFlagBits::VERTEX)
-                .module(vert_shader_module)
+                .module(*vert_shader_module)
                 .name(&entry_point),
             vk::PipelineShaderStageCreateInfoBuilder::new()
                 .stage(vk::ShaderStageFlagBits::FRAGMENT)
-                .module(frag_shader_module)
+                .module(*frag_shader_module)
                 .name(&entry_point),
         ];
 
         let input_binding_descriptions = [vk::VertexInputBindingDescriptionBuilder::new()
             .binding(0)
-            .stride(std::mem::size_of::<u32>() as u32)
+            .stride(std::mem::size_of::<crate::voxel::Face>() as u32)
             .input_rate(vk::VertexInputRate::INSTANCE)];
 
-        let input_attribute_descriptions = [vk::VertexInputAttributeDescriptionBuilder::new()
-            .binding(0)
-            .location(0)
-            .format(vk::Format::R32_UINT)
-            .offset(0)];
+        let input_attribute_descriptions = [
+            vk::VertexInputAttributeDescriptionBuilder::new()
+                .binding(0)
+                .location(0)
+                .format(vk::Format::R32_UINT)
+                .offset(0),
+            vk::VertexInputAttributeDescriptionBuilder::new()
+                .binding(0)
+                .location(1)
+                .format(vk::Format::R8G8B8A8_UNORM)
+                .offset(std::mem::size_of::<u32>() as u32),
+            vk::VertexInputAttributeDescriptionBuilder::new()
+                .binding(0)
+                .location(2)
+                .format(vk::Format::R32_UINT)
+                .offset(2 * std::mem::size_of::<u32>() as u32),
+            vk::VertexInputAttributeDescriptionBuilder::new()
+                .binding(0)
+                .location(3)
+                .format(vk::Format::R32_UINT)
+                .offset(3 * std::mem::size_of::<u32>() as u32),
+        ];
 
         let vertex_input = vk::PipelineVertexInputStateCreateInfoBuilder::new()
             .vertex_binding_descriptions(&input_binding_descriptions)
@@ -104,13 +354,17 @@ impl VoxelRenderer {
 
         let push_constant_ranges = [vk::PushConstantRangeBuilder::new()
             .offset(0)
-            .size(64)
+            .size(Self::PUSH_CONSTANTS_SIZE)
             .stage_flags(vk::ShaderStageFlags::VERTEX)];
 
-        let layout_info =
-            vk::PipelineLayoutCreateInfoBuilder::new().push_constant_ranges(&push_constant_ranges);
+        let layout_info = vk::PipelineLayoutCreateInfoBuilder::new()
+            .set_layouts(std::slice::from_ref(&descriptor_set_layout))
+            .push_constant_ranges(&push_constant_ranges);
 
-        let layout = unsafe { device.create_pipeline_layout(&layout_info, None) }.unwrap();
+        let layout = super::Owned::new(
+            device,
+            unsafe { device.create_pipeline_layout(&layout_info, None) }.result()?,
+        );
 
         let pipeline_create_info = vk::GraphicsPipelineCreateInfoBuilder::new()
             .stages(&stages)
@@ -121,56 +375,290 @@ impl VoxelRenderer {
             .rasterization_state(&rasterization)
             .multisample_state(&multisample)
             .color_blend_state(&color_blend)
-            .layout(layout)
+            .layout(*layout)
             .render_pass(render_info.render_pass)
             .subpass(0);
 
         let pipeline =
             unsafe { device.create_graphics_pipelines(None, &[pipeline_create_info], None) }
-                .unwrap()[0];
+                .result()?[0];
 
-        unsafe {
-            device.destroy_shader_module(Some(vert_shader_module), None);
-            device.destroy_shader_module(Some(frag_shader_module), None);
+        Ok((layout.keep(), pipeline))
+    }
+
+    // Replaces the mesh of a chunk. The new faces are uploaded by the next `prepass`, and the old
+    // ones stay in the pool until no frame in flight uses them.
+    pub fn set_chunk(
+        &mut self,
+        coord: crate::voxel::ChunkCoord,
+        faces: &[crate::voxel::Face],
+    ) -> Result<(), super::RenderError> {
+        if faces.is_empty() {
+            self.remove_chunk(coord)
+        } else {
+            let range = self.faces.insert(faces)?;
+            if let Some(old) = self.meshes.insert(coord, range) {
+                self.retired.push((self.frame, old))
+            }
         }
+        Ok(())
+    }
 
-        let mesh = VoxelMesh::from_faces(instance.clone(), &faces);
+    pub fn remove_chunk(&mut self, coord: crate::voxel::ChunkCoord) {
+        if let Some(old) = self.meshes.remove(&coord) {
+            self.retired.push((self.frame, old))
+        }
+    }
 
-        Self {
-            instance,
-            layout,
-            pipeline,
-            mesh,
+    pub fn clear(&mut self) {
+        let frame = self.frame;
+        self.retired
+            .extend(self.meshes.drain().map(|(_, range)| (frame, range)))
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.meshes.values().map(|range| range.length as usize).sum()
+    }
+
+    // Starts a new frame lit by `sun` and seen through `matrix`. Must be called once before each
+    // `Renderer::render`, whose prepass should call `prepass` and whose recorder should call
+    // `draw`.
+    pub fn prepare(
+        &mut self,
+        sun: &crate::render::Sun,
+        matrix: &ultraviolet::Mat4,
+    ) -> Result<(), super::RenderError> {
+        // This runs before the renderer waits for the frame MAX_FRAMES_IN_FLIGHT draws ago, so
+        // only faces retired before that one are known to be unused.
+        self.frame += 1;
+        let frame = self.frame;
+        let faces = &mut self.faces;
+        self.retired.retain(|&(retired, range)| {
+            let in_use = frame - retired <= super::Renderer::MAX_FRAMES_IN_FLIGHT;
+            if !in_use {
+                faces.remove(range)
+            }
+            in_use
+        });
+
+        self.chunk_table.clear();
+        self.chunk_table.extend(
+            self.meshes
+                .iter()
+                .map(|(&coord, &range)| ChunkInfo::new(coord, range)),
+        );
+        self.commands.clear();
+        self.commands.extend(
+            self.chunk_table
+                .iter()
+                .enumerate()
+                .map(|(index, chunk)| chunk.command(index)),
+        );
+
+        self.view_matrix = *matrix;
+        if self.culling == Culling::Cpu {
+            let frustum = crate::render::Frustum::from_matrix(matrix);
+            let visible = self
+                .chunk_table
+                .iter()
+                .enumerate()
+                .filter(|(_, chunk)| {
+                    let (min, max) = chunk.bounds();
+                    frustum.intersects_box(min, max)
+                })
+                .map(|(index, chunk)| chunk.command(index))
+                .collect::<Vec<_>>();
+            self.counts = ChunkCounts {
+                drawn: visible.len(),
+                culled: self.chunk_table.len() - visible.len(),
+            };
+            self.commands.extend(visible)
+        }
+        let staged = self.faces.staged_len();
+        self.reserve(self.chunk_table.len(), staged)?;
+
+        // Fit the shadow map around every chunk with a mesh.
+        let bounds = self.chunk_table.iter().fold(None, |bounds, chunk| {
+            let (min, max) = chunk.bounds();
+            Some(match bounds {
+                None => (min, max),
+                Some((lo, hi)) => (min.min_by_component(lo), max.max_by_component(hi)),
+            })
+        });
+        let (min, max) = bounds.unwrap_or_default();
+        self.lighting = Lighting::new(sun, sun.matrix(min, max));
+        Ok(())
+    }
+
+    // Makes room for `chunk_count` chunks and `face_count` staged faces in every frame's buffers.
+    // Waits for the device to go idle if they have to grow, since frames in flight may still be
+    // reading the old ones.
+    fn reserve(&mut self, chunk_count: usize, face_count: usize) -> Result<(), super::RenderError> {
+        let chunk_capacity = self.frames[0].chunk_table.capacity();
+        let staging_capacity = self.frames[0].staging.capacity();
+        if chunk_capacity >= chunk_count && staging_capacity >= face_count {
+            return Ok(());
+        }
+        let chunk_capacity = chunk_capacity.max(chunk_count.next_power_of_two());
+        let staging_capacity = staging_capacity.max(face_count.next_power_of_two());
+        self.instance.wait_idle()?;
+        for frame in &mut self.frames {
+            *frame = FrameResources::new(
+                self.instance.clone(),
+                frame.descriptor_set,
+                chunk_capacity,
+                staging_capacity,
+            )?
         }
+        self.write_descriptor_sets();
+        Ok(())
     }
 
-    pub fn draw(&mut self, command_buffer: vk::CommandBuffer, matrix: &ultraviolet::Mat4) {
+    fn current_frame(&self) -> &FrameResources {
+        &self.frames[self.frame % super::Renderer::MAX_FRAMES_IN_FLIGHT]
+    }
+
+    // Uploads new chunk meshes, culls chunks on the GPU if enabled, then renders the shadow map, for
+    // the frame started by `prepare`. Must be recorded outside of a render pass.
+    pub fn prepass(&self, command_buffer: vk::CommandBuffer) {
+        // The renderer has waited for the last frame that used these buffers.
+        let frame = self.current_frame();
+        self.faces.record_uploads(command_buffer, &frame.staging);
+        frame.lighting.write(0, &[self.lighting]);
+        frame.chunk_table.write(0, &self.chunk_table);
+        frame.commands.write(0, &self.commands);
+
+        let chunk_count = self.chunk_table.len() as u32;
+        if self.culling == Culling::Gpu && chunk_count > 0 {
+            let frustum = crate::render::Frustum::from_matrix(&self.view_matrix);
+            self.cull_pipeline.record(
+                command_buffer,
+                frame.descriptor_set,
+                &frustum,
+                chunk_count,
+                chunk_count,
+            )
+        }
+
+        self.shadow_map
+            .record(command_buffer, &self.lighting.light_matrix, |layout| {
+                unsafe {
+                    self.instance.device().cmd_bind_descriptor_sets(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        layout,
+                        0,
+                        &[frame.descriptor_set],
+                        &[],
+                    )
+                }
+                // Chunks outside the view can still cast shadows into it.
+                self.draw_commands(command_buffer, 0, chunk_count)
+            })
+    }
+
+    // Draws the chunks in view of the matrix given to `prepare`. With GPU culling, the returned
+    // counts are from the last frame that used this frame's buffers, since the GPU hasn't culled
+    // this one yet.
+    pub fn draw(&self, command_buffer: vk::CommandBuffer) -> ChunkCounts {
         let device = self.instance.device();
+        let frame = self.current_frame();
         unsafe {
             device.cmd_bind_pipeline(
                 command_buffer,
                 vk::PipelineBindPoint::GRAPHICS,
                 self.pipeline,
             );
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.layout,
+                0,
+                &[frame.descriptor_set],
+                &[],
+            );
             device.cmd_push_constants(
                 command_buffer,
                 self.layout,
                 vk::ShaderStageFlags::VERTEX,
                 0,
-                64,
-                matrix.as_ptr().cast(),
+                Self::PUSH_CONSTANTS_SIZE,
+                self.view_matrix.as_ptr().cast(),
             );
+        }
+
+        // The main pass's commands follow the shadow pass's.
+        let chunk_count = self.chunk_table.len() as u32;
+        let [drawn, culled] = frame.counts.read(0);
+        frame.counts.write(0, &[[0, 0]]);
+        match self.culling {
+            Culling::Cpu => {
+                self.draw_commands(
+                    command_buffer,
+                    chunk_count,
+                    self.commands.len() as u32 - chunk_count,
+                );
+                self.counts
+            }
+            Culling::Gpu => {
+                self.draw_commands(command_buffer, chunk_count, chunk_count);
+                ChunkCounts {
+                    drawn: drawn as usize,
+                    culled: culled as usize,
+                }
+            }
+        }
+    }
 
-            device.cmd_bind_vertex_buffers(command_buffer, 0, &[self.mesh.vertex_buffer], &[0]);
-            device.cmd_draw(command_buffer, 6, self.mesh.length, 0, 0)
+    // Draws `count` of the current frame's indirect commands, starting at `first`, with a bound
+    // pipeline and descriptor set.
+    fn draw_commands(&self, command_buffer: vk::CommandBuffer, first: u32, count: u32) {
+        if count == 0 {
+            return;
+        }
+        let device = self.instance.device();
+        unsafe { device.cmd_bind_vertex_buffers(command_buffer, 0, &[self.faces.buffer()], &[0]) }
+        if self.instance.multi_draw_indirect() {
+            let stride = std::mem::size_of::<vk::DrawIndirectCommand>() as u32;
+            unsafe {
+                device.cmd_draw_indirect(
+                    command_buffer,
+                    self.current_frame().commands.buffer(),
+                    (first * stride) as u64,
+                    count,
+                    stride,
+                )
+            }
+        } else {
+            // GPU culling isn't allowed without multi-draw, so all the commands are on the CPU.
+            for command in &self.commands[first as usize..(first + count) as usize] {
+                unsafe {
+                    device.cmd_draw(
+                        command_buffer,
+                        command.vertex_count,
+                        command.instance_count,
+                        command.first_vertex,
+                        command.first_instance,
+                    )
+                }
+            }
         }
     }
 
-    pub fn rebuild(&mut self, surface: &super::Surface, faces: &[u32]) {
+    pub fn rebuild<T: super::Target>(&mut self, target: &T) -> Result<(), super::RenderError> {
+        let (layout, pipeline) = Self::create_pipeline(
+            &self.instance,
+            self.descriptor_set_layout,
+            target.render_info(),
+        )?;
+        let device = self.instance.device();
         unsafe {
-            std::mem::drop(std::ptr::read(self));
-            std::ptr::write(self, Self::new(surface, faces))
+            device.destroy_pipeline_layout(Some(self.layout), None);
+            device.destroy_pipeline(Some(self.pipeline), None)
         }
+        self.layout = layout;
+        self.pipeline = pipeline;
+        Ok(())
     }
 }
 
@@ -178,60 +666,151 @@ impl Drop for VoxelRenderer {
     fn drop(&mut self) {
         let device = self.instance.device();
         unsafe {
-            device.free_memory(Some(self.mesh.vertex_buffer_memory), None);
-            device.destroy_buffer(Some(self.mesh.vertex_buffer), None);
             device.destroy_pipeline_layout(Some(self.layout), None);
-            device.destroy_pipeline(Some(self.pipeline), None)
+            device.destroy_pipeline(Some(self.pipeline), None);
+            device.destroy_sampler(Some(self.sampler), None);
+            device.destroy_descriptor_pool(Some(self.descriptor_pool), None);
+            device.destroy_descriptor_set_layout(Some(self.descriptor_set_layout), None)
         }
     }
 }
 
-pub struct VoxelMesh {
-    vertex_buffer: vk::Buffer,
-    vertex_buffer_memory: vk::DeviceMemory,
-    length: u32,
+// Where chunks out of view are culled. On the CPU, only chunks in view get a draw command. On the
+// GPU, `cull.comp` writes a command for every chunk, with no instances for the ones out of view.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Culling {
+    Cpu,
+    Gpu,
 }
 
-impl VoxelMesh {
-    pub fn from_faces(instance: std::rc::Rc<super::Instance>, faces: &[u32]) -> Self {
-        let device = instance.device();
-
-        let buffer_info = vk::BufferCreateInfoBuilder::new()
-            .size((std::mem::size_of::<u32>() * faces.len()) as u64)
-            .usage(vk::BufferUsageFlags::VERTEX_BUFFER)
-            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+impl Culling {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Cpu => Self::Gpu,
+            Self::Gpu => Self::Cpu,
+        }
+    }
+}
 
-        let vertex_buffer = unsafe { device.create_buffer(&buffer_info, None) }.unwrap();
+impl std::str::FromStr for Culling {
+    type Err = String;
 
-        let requirements = unsafe { device.get_buffer_memory_requirements(vertex_buffer) };
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cpu" => Ok(Self::Cpu),
+            "gpu" => Ok(Self::Gpu),
+            _ => Err(format!("unknown culling mode `{}`", s)),
+        }
+    }
+}
 
-        let memory_type_index = instance.get_memory_type_index(
-            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
-            requirements,
-        );
+// How many chunks a `VoxelRenderer::draw` call drew, and how many it skipped for being out of view.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ChunkCounts {
+    pub drawn: usize,
+    pub culled: usize,
+}
 
-        let memory_info = vk::MemoryAllocateInfoBuilder::new()
-            .allocation_size(requirements.size)
-            .memory_type_index(memory_type_index);
+// Matches the `lighting` uniform block in voxel.vert and voxel.frag.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Lighting {
+    light_matrix: ultraviolet::Mat4,
+    sun_direction: ultraviolet::Vec4,
+    sun_color: ultraviolet::Vec4, // ambient in w
+}
 
-        let vertex_buffer_memory =
-            unsafe { device.allocate_memory(&memory_info, None) }.unwrap();
+impl Lighting {
+    fn new(sun: &crate::render::Sun, light_matrix: ultraviolet::Mat4) -> Self {
+        let [r, g, b] = sun.color;
+        Self {
+            light_matrix,
+            sun_direction: sun.direction().into_homogeneous_vector(),
+            sun_color: ultraviolet::Vec4::new(r, g, b, sun.ambient),
+        }
+    }
+}
 
-        unsafe { device.bind_buffer_memory(vertex_buffer, vertex_buffer_memory, 0) }.unwrap();
+// Matches `ChunkInfo` in voxel.vert, shadow.vert and cull.comp.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ChunkInfo {
+    origin: ultraviolet::Vec4,
+    first_face: u32,
+    face_count: u32,
+    padding: [u32; 2], // std430 rounds the size up to a multiple of the vec4's alignment
+}
 
-        let mut memory: *mut std::ffi::c_void = std::ptr::null_mut::<std::ffi::c_void>();
+impl ChunkInfo {
+    fn new(coord: crate::voxel::ChunkCoord, faces: super::FaceRange) -> Self {
+        let [x, y, z] = coord.origin();
+        Self {
+            origin: ultraviolet::Vec4::new(x as f32, y as f32, z as f32, 0.),
+            first_face: faces.offset,
+            face_count: faces.length,
+            padding: [0; 2],
+        }
+    }
 
-        unsafe { device.map_memory(vertex_buffer_memory, 0, buffer_info.size, None, &mut memory) }
-            .unwrap();
+    fn bounds(&self) -> (ultraviolet::Vec3, ultraviolet::Vec3) {
+        // Voxels are centered on whole coordinates.
+        let min = self.origin.xyz() - ultraviolet::Vec3::broadcast(0.5);
+        (
+            min,
+            min + ultraviolet::Vec3::broadcast(crate::voxel::Chunk::SIZE as f32),
+        )
+    }
 
-        unsafe { std::ptr::copy_nonoverlapping(faces.as_ptr(), memory.cast(), faces.len()) };
+    // Draws the chunk's faces as entry `index` of the chunk table. Must match cull.comp.
+    fn command(&self, index: usize) -> vk::DrawIndirectCommand {
+        vk::DrawIndirectCommand {
+            vertex_count: 6,
+            instance_count: self.face_count,
+            first_vertex: 6 * index as u32,
+            first_instance: self.first_face,
+        }
+    }
+}
 
-        unsafe { device.unmap_memory(vertex_buffer_memory) };
+// The buffers one frame in flight reads, and the descriptor set pointing at them.
+struct FrameResources {
+    descriptor_set: vk::DescriptorSet,
+    lighting: super::HostBuffer<Lighting>,
+    chunk_table: super::HostBuffer<ChunkInfo>,
+    commands: super::HostBuffer<vk::DrawIndirectCommand>, // two per chunk, see `VoxelRenderer`
+    counts: super::HostBuffer<[u32; 2]>,                  // drawn and culled, from cull.comp
+    staging: super::HostBuffer<crate::voxel::Face>,       // new faces on their way to the pool
+}
 
-        Self {
-            vertex_buffer,
-            vertex_buffer_memory,
-            length: faces.len() as u32,
-        }
+impl FrameResources {
+    fn new(
+        instance: std::rc::Rc<super::Instance>,
+        descriptor_set: vk::DescriptorSet,
+        chunk_capacity: usize,
+        staging_capacity: usize,
+    ) -> Result<Self, super::RenderError> {
+        let storage = vk::BufferUsageFlags::STORAGE_BUFFER;
+        let counts = super::HostBuffer::new(instance.clone(), storage, 1)?;
+        counts.write(0, &[[0, 0]]);
+        Ok(Self {
+            descriptor_set,
+            lighting: super::HostBuffer::new(
+                instance.clone(),
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+                1,
+            )?,
+            chunk_table: super::HostBuffer::new(instance.clone(), storage, chunk_capacity)?,
+            commands: super::HostBuffer::new(
+                instance.clone(),
+                storage | vk::BufferUsageFlags::INDIRECT_BUFFER,
+                2 * chunk_capacity,
+            )?,
+            counts,
+            staging: super::HostBuffer::new(
+                instance,
+                vk::BufferUsageFlags::TRANSFER_SRC,
+                staging_capacity,
+            )?,
+        })
     }
 }
@@ -0,0 +1,21 @@
+#version 450
+
+layout(push_constant) uniform push_constants {
+    mat4 viewProjection;
+    vec4 voxel;
+};
+
+// The 12 edges of a cube as pairs of corners, where bit 0/1/2 of a corner selects its x/y/z side.
+int edgeCorners[24] = int[](
+0, 1, 2, 3, 4, 5, 6, 7, // along x
+0, 2, 1, 3, 4, 6, 5, 7, // along y
+0, 4, 1, 5, 2, 6, 3, 7  // along z
+);
+
+void main() {
+    int corner = edgeCorners[gl_VertexIndex];
+    vec3 cornerOffset = vec3(corner & 1, (corner >> 1) & 1, (corner >> 2) & 1) - 0.5;
+
+    // Slightly larger than the voxel so the lines aren't hidden by its faces.
+    gl_Position = viewProjection * vec4(voxel.xyz + cornerOffset * 1.01, 1);
+}
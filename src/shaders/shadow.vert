@@ -0,0 +1,42 @@
+#version 450
+
+layout(location = 0) in uint data;
+layout(push_constant) uniform push_constants {
+    mat4 lightMatrix;
+};
+// Matches `ChunkInfo` in voxel_renderer.rs. Chunk `i` is drawn with vertices 6i to 6i + 5, so each
+// vertex can look up which chunk it's in.
+struct ChunkInfo {
+    vec4 origin;
+    uint firstFace;
+    uint faceCount;
+};
+layout(std430, set = 0, binding = 3) readonly buffer chunk_table {
+    ChunkInfo chunks[];
+};
+
+// Must match voxel.vert. The split diagonal doesn't matter for depth, so quads are never flipped.
+float cornerIndicesI[6] = float[](0, 0, 1, 1, 1, 0);
+float cornerIndicesJ[6] = float[](0, 1, 0, 1, 0, 1);
+
+mat4 faceTransforms[6] = mat4[](
+mat4(0, 1, 0, 0, 0, 0, 1, 0,  1, 0, 0, 0, 0, 0, 0, 1), // +x
+mat4(0, 0, 1, 0, 0, 1, 0, 0, -1, 0, 0, 0, 0, 0, 0, 1), // -x
+mat4(0, 0, 1, 0, 1, 0, 0, 0, 0,  1, 0, 0, 0, 0, 0, 1), // +y
+mat4(1, 0, 0, 0, 0, 0, 1, 0, 0, -1, 0, 0, 0, 0, 0, 1), // -y
+mat4(1, 0, 0, 0, 0, 1, 0, 0, 0, 0,  1, 0, 0, 0, 0, 1), // +z
+mat4(0, 1, 0, 0, 1, 0, 0, 0, 0, 0, -1, 0, 0, 0, 0, 1)  // -z
+);
+
+void main() {
+    uint vertex = uint(gl_VertexIndex) % 6u;
+    vec4 chunkOffset = chunks[uint(gl_VertexIndex) / 6u].origin;
+    uint direction = bitfieldExtract(data, 15, 3);
+
+    vec4 chunkCubeCoord = vec4(bitfieldExtract(data, 0, 5), bitfieldExtract(data, 5, 5), bitfieldExtract(data, 10, 5), 0);
+    vec2 faceSize = vec2(bitfieldExtract(data, 18, 5) + 1u, bitfieldExtract(data, 23, 5) + 1u);
+    vec4 faceVertexCoord = vec4(vec2(cornerIndicesI[vertex], cornerIndicesJ[vertex]) * faceSize - 0.5, 0.5, 1);
+    vec4 cubeVertexCoord = faceTransforms[direction] * faceVertexCoord;
+
+    gl_Position = lightMatrix * (chunkCubeCoord + cubeVertexCoord + chunkOffset);
+}
@@ -1,9 +1,36 @@
 #version 450
 
 layout(location = 0) in vec3 fragColor;
+layout(location = 1) in vec2 fragUV;
+layout(location = 2) flat in uint fragLayer;
+layout(location = 3) in float fragAO;
+layout(location = 4) flat in float fragAmbient;
+layout(location = 5) flat in float fragDiffuse;
+layout(location = 6) in vec4 fragShadowCoord;
+layout(location = 7) flat in float fragSkyLight;
+layout(location = 8) flat in float fragBlockLight;
+
+layout(set = 0, binding = 0) uniform sampler2DArray textures;
+layout(set = 0, binding = 1) uniform sampler2DShadow shadowMap;
+layout(set = 0, binding = 2) uniform lighting {
+    mat4 lightMatrix;
+    vec4 sunDirection;
+    vec4 sunColor; // ambient in w
+};
 
 layout(location = 0) out vec4 outColor;
 
+// The color of light from emissive blocks.
+const vec3 blockLightColor = vec3(1.0, 0.85, 0.6);
+
 void main() {
-    outColor = vec4(fragColor, 1.0);
+    // 1 where the sun reaches this fragment, 0 in shadow. Only faces turned towards the sun can be lit.
+    float shadow = 0;
+    if (fragDiffuse > 0) {
+        vec3 shadowCoord = fragShadowCoord.xyz / fragShadowCoord.w;
+        shadow = texture(shadowMap, vec3(shadowCoord.xy * 0.5 + 0.5, shadowCoord.z));
+    }
+    vec3 light = (fragAmbient + sunColor.rgb * fragDiffuse * shadow) * fragSkyLight
+        + blockLightColor * fragBlockLight;
+    outColor = vec4(fragColor * fragAO * light * texture(textures, vec3(fragUV, fragLayer)).rgb, 1.0);
 }
@@ -1,22 +1,57 @@
 #version 450
 
 layout(location = 0) in uint data;
+layout(location = 1) in vec4 color;
+layout(location = 2) in uint layer;
+layout(location = 3) in uint lighting;
 layout(location = 0) out vec3 fragColor;
+layout(location = 1) out vec2 fragUV;
+layout(location = 2) flat out uint fragLayer;
+layout(location = 3) out float fragAO;
+layout(location = 4) flat out float fragAmbient;
+layout(location = 5) flat out float fragDiffuse;
+layout(location = 6) out vec4 fragShadowCoord;
+layout(location = 7) flat out float fragSkyLight;
+layout(location = 8) flat out float fragBlockLight;
 layout(push_constant) uniform push_constants {
-    mat4 chunkTransform;
+    mat4 viewProjection;
+};
+// Matches `ChunkInfo` in voxel_renderer.rs. Chunk `i` is drawn with vertices 6i to 6i + 5, so each
+// vertex can look up which chunk it's in.
+struct ChunkInfo {
+    vec4 origin;
+    uint firstFace;
+    uint faceCount;
+};
+layout(std430, set = 0, binding = 3) readonly buffer chunk_table {
+    ChunkInfo chunks[];
+};
+layout(set = 0, binding = 2) uniform lighting {
+    mat4 lightMatrix;
+    vec4 sunDirection;
+    vec4 sunColor; // ambient in w
 };
 
-vec3 colors[6] = vec3[](
-vec3(1.0, 0.0, 0.0),
-vec3(1.0, 0.5, 0.0),
-vec3(1.0, 1.0, 0.0),
-vec3(0.0, 1.0, 0.0),
-vec3(0.0, 0.0, 1.0),
-vec3(1.0, 0.0, 1.0)
-);
+float shades[6] = float[](0.8, 0.7, 0.85, 0.75, 1.0, 0.6);
+
+vec3 normals[6] = vec3[](vec3(1, 0, 0), vec3(-1, 0, 0), vec3(0, 1, 0), vec3(0, -1, 0), vec3(0, 0, 1), vec3(0, 0, -1));
+
+// Brightness of flood-fill light levels 0 to 15, each level 80% as bright as the one above.
+float lightLevel(uint level) {
+    return pow(0.8, float(15u - level));
+}
 
-float cornerIndicesI[6] = float[](-0.5, -0.5,  0.5, 0.5,  0.5, -0.5);
-float cornerIndicesJ[6] = float[](-0.5,  0.5, -0.5, 0.5, -0.5,  0.5);
+// How far shadow lookups are pushed out of the face, so lit faces don't shadow themselves.
+const float shadowNormalOffset = 0.05;
+
+// Brightness for ambient occlusion levels 0 (most occluded) to 3.
+float aoLevels[4] = float[](0.45, 0.65, 0.82, 1.0);
+
+// Two triangles split along the 10-01 diagonal, or along 00-11 when flipped.
+float cornerIndicesI[6] = float[](0, 0, 1, 1, 1, 0);
+float cornerIndicesJ[6] = float[](0, 1, 0, 1, 0, 1);
+float flippedCornerIndicesI[6] = float[](0, 0, 1, 0, 1, 1);
+float flippedCornerIndicesJ[6] = float[](0, 1, 1, 0, 1, 0);
 
 mat4 faceTransforms[6] = mat4[](
 mat4(0, 1, 0, 0, 0, 0, 1, 0,  1, 0, 0, 0, 0, 0, 0, 1), // +x
@@ -27,14 +62,44 @@ mat4(1, 0, 0, 0, 0, 1, 0, 0, 0, 0,  1, 0, 0, 0, 0, 1), // +z
 mat4(0, 1, 0, 0, 1, 0, 0, 0, 0, 0, -1, 0, 0, 0, 0, 1)  // -z
 );
 
+// Texture axes per direction, so that textures are upright on the sides when seen from outside.
+vec3 uAxes[6] = vec3[](vec3(0, 1, 0), vec3(0, -1, 0), vec3(-1, 0, 0), vec3(1, 0, 0), vec3(1, 0, 0), vec3(1, 0, 0));
+vec3 vAxes[6] = vec3[](vec3(0, 0, -1), vec3(0, 0, -1), vec3(0, 0, -1), vec3(0, 0, -1), vec3(0, -1, 0), vec3(0, 1, 0));
+
 void main() {
+    uint vertex = uint(gl_VertexIndex) % 6u;
+    vec4 chunkOffset = chunks[uint(gl_VertexIndex) / 6u].origin;
     uint direction = bitfieldExtract(data, 15, 3);
 
     vec4 chunkCubeCoord = vec4(bitfieldExtract(data, 0, 5), bitfieldExtract(data, 5, 5), bitfieldExtract(data, 10, 5), 0);
-    vec4 faceVertexCoord = vec4(cornerIndicesI[gl_VertexIndex], cornerIndicesJ[gl_VertexIndex], 0.5, 1);
+    vec2 faceSize = vec2(bitfieldExtract(data, 18, 5) + 1u, bitfieldExtract(data, 23, 5) + 1u);
+    bool flip = bitfieldExtract(lighting, 8, 1) != 0u;
+    vec2 corner = flip
+        ? vec2(flippedCornerIndicesI[vertex], flippedCornerIndicesJ[vertex])
+        : vec2(cornerIndicesI[vertex], cornerIndicesJ[vertex]);
+    vec4 faceVertexCoord = vec4(corner * faceSize - 0.5, 0.5, 1);
     vec4 cubeVertexCoord = faceTransforms[direction] * faceVertexCoord;
     vec4 vertexChunkCoord = chunkCubeCoord + cubeVertexCoord;
 
-    gl_Position = chunkTransform * vertexChunkCoord;
-    fragColor = colors[direction];
+    vec4 position = vertexChunkCoord + chunkOffset;
+    gl_Position = viewProjection * position;
+    fragColor = pow(color.rgb, vec3(2.2));
+
+    // The direction shades still tell faces apart where only ambient light reaches.
+    fragAmbient = sunColor.w * shades[direction];
+    fragDiffuse = (1 - sunColor.w) * max(dot(normals[direction], sunDirection.xyz), 0);
+    fragShadowCoord = lightMatrix * (position + vec4(normals[direction] * shadowNormalOffset, 0));
+
+    // Sky light dims the sun and ambient light where the sky is hidden, and block light adds to them.
+    uint blockLight = bitfieldExtract(lighting, 16, 4);
+    fragSkyLight = lightLevel(bitfieldExtract(lighting, 20, 4));
+    fragBlockLight = blockLight == 0u ? 0 : lightLevel(blockLight);
+
+    // Voxel corners sit on whole numbers here, so each voxel covers one repeat of the texture.
+    vec3 voxelCorner = vertexChunkCoord.xyz + 0.5;
+    fragUV = vec2(dot(voxelCorner, uAxes[direction]), dot(voxelCorner, vAxes[direction]));
+    fragLayer = layer;
+
+    uint cornerIndex = uint(corner.x) + 2u * uint(corner.y);
+    fragAO = aoLevels[bitfieldExtract(lighting, int(2u * cornerIndex), 2)];
 }
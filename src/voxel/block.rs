@@ -0,0 +1,343 @@
+pub struct BlockRegistry {
+    blocks: Vec<Option<BlockDef>>,
+    missing: BlockDef,
+    textures: Vec<String>, // layer `i + 1` of the texture array, layer 0 is plain white
+    layers: Vec<[u32; 6]>, // per block, per face direction
+    texture_dir: std::path::PathBuf,
+}
+
+#[derive(Clone, Debug)]
+pub struct BlockDef {
+    pub name: String,
+    pub solid: bool,
+    pub opaque: bool,
+    pub transparent: bool,
+    pub emissive: u8, // light level, 0..=15
+    pub color: [u8; 4],
+    pub textures: [Option<String>; 6], // per face direction, multiplied with `color`
+}
+
+impl BlockDef {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            solid: true,
+            opaque: true,
+            transparent: false,
+            emissive: 0,
+            color: [0xff, 0xff, 0xff, 0xff],
+            textures: Default::default(),
+        }
+    }
+
+    fn air() -> Self {
+        Self {
+            solid: false,
+            opaque: false,
+            transparent: true,
+            color: [0, 0, 0, 0],
+            ..Self::new("Air".to_string())
+        }
+    }
+
+    fn missing() -> Self {
+        Self {
+            color: [0xff, 0x00, 0xff, 0xff],
+            ..Self::new("Missing".to_string())
+        }
+    }
+
+    pub fn packed_color(&self) -> u32 {
+        u32::from_le_bytes(self.color)
+    }
+}
+
+impl BlockRegistry {
+    pub const AIR: super::Block = 0;
+
+    const BUILTIN: &'static str = include_str!("blocks.ini");
+
+    pub fn builtin() -> Self {
+        Self::parse(Self::BUILTIN).unwrap()
+    }
+
+    // Texture paths are resolved relative to the directory containing the file.
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self, RegistryError> {
+        let path = path.as_ref();
+        let mut registry = Self::parse(&std::fs::read_to_string(path)?)?;
+        registry.texture_dir = path.parent().unwrap_or(std::path::Path::new("")).to_path_buf();
+        Ok(registry)
+    }
+
+    // Format: one `[id]` section per block, followed by `key = value` lines.
+    pub fn parse(source: &str) -> Result<Self, RegistryError> {
+        let mut registry = Self::with_blocks(vec![Some(BlockDef::air())]);
+        let mut current: Option<super::Block> = None;
+        // Per block, the `texture` value and the per-face ones, resolved once everything is read.
+        let mut textures = std::collections::HashMap::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: String| RegistryError::Parse {
+                line: line_number,
+                message,
+            };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(section) = line.strip_prefix('[') {
+                let id = section
+                    .strip_suffix(']')
+                    .and_then(|id| id.trim().parse::<super::Block>().ok())
+                    .ok_or_else(|| error(format!("invalid section header `{}`", line)))?;
+                if id == Self::AIR {
+                    return Err(error("block 0 is reserved for air".to_string()));
+                }
+                if registry.blocks.len() <= id as usize {
+                    registry.blocks.resize(id as usize + 1, None);
+                }
+                if registry.blocks[id as usize].is_some() {
+                    return Err(error(format!("block {} is defined twice", id)));
+                }
+                registry.blocks[id as usize] = Some(BlockDef::new(format!("Block {}", id)));
+                current = Some(id);
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(error(format!("expected `key = value`, got `{}`", line))),
+            };
+            let (id, def) = match current {
+                Some(id) => (id, registry.blocks[id as usize].as_mut().unwrap()),
+                None => return Err(error(format!("`{}` outside of a block section", key))),
+            };
+            let parse_bool = |value: &str| match value {
+                "true" => Ok(true),
+                "false" => Ok(false),
+                _ => Err(error(format!(
+                    "expected `true` or `false`, got `{}`",
+                    value
+                ))),
+            };
+
+            match key {
+                "name" => def.name = value.to_string(),
+                "solid" => def.solid = parse_bool(value)?,
+                "opaque" => def.opaque = parse_bool(value)?,
+                "transparent" => def.transparent = parse_bool(value)?,
+                "emissive" => {
+                    def.emissive = value
+                        .parse()
+                        .ok()
+                        .filter(|&level| level <= 15)
+                        .ok_or_else(|| error(format!("invalid light level `{}`", value)))?
+                }
+                "color" => {
+                    def.color = parse_color(value)
+                        .ok_or_else(|| error(format!("invalid color `{}`", value)))?
+                }
+                "texture" | "texture_side" | "texture_top" | "texture_bottom" => {
+                    let (base, faces): &mut (Option<String>, [Option<String>; 6]) =
+                        textures.entry(id).or_default();
+                    let value = Some(value.to_string());
+                    match key {
+                        "texture" => *base = value,
+                        "texture_side" => faces[..4].fill(value),
+                        "texture_top" => faces[4] = value,
+                        _ => faces[5] = value,
+                    }
+                }
+                _ => return Err(error(format!("unknown key `{}`", key))),
+            }
+        }
+
+        // Per-face textures override `texture`, whichever comes first in the section.
+        for (id, (base, faces)) in textures {
+            let def = registry.blocks[id as usize].as_mut().unwrap();
+            for (texture, face) in def.textures.iter_mut().zip(faces.iter()) {
+                *texture = face.clone().or_else(|| base.clone())
+            }
+        }
+
+        registry.assign_layers();
+        Ok(registry)
+    }
+
+    fn with_blocks(blocks: Vec<Option<BlockDef>>) -> Self {
+        let mut registry = Self {
+            blocks,
+            missing: BlockDef::missing(),
+            textures: Vec::new(),
+            layers: Vec::new(),
+            texture_dir: std::path::PathBuf::new(),
+        };
+        registry.assign_layers();
+        registry
+    }
+
+    // Gives each distinct texture name a layer, in order of first use.
+    fn assign_layers(&mut self) {
+        let textures = &mut self.textures;
+        textures.clear();
+        self.layers = self
+            .blocks
+            .iter()
+            .map(|def| {
+                let mut layers = [0; 6];
+                if let Some(def) = def {
+                    for (layer, texture) in layers.iter_mut().zip(&def.textures) {
+                        if let Some(texture) = texture {
+                            let index = match textures.iter().position(|t| t == texture) {
+                                Some(index) => index,
+                                None => {
+                                    textures.push(texture.clone());
+                                    textures.len() - 1
+                                }
+                            };
+                            *layer = index as u32 + 1
+                        }
+                    }
+                }
+                layers
+            })
+            .collect()
+    }
+
+    // One opaque block per color, with block `i` using `colors[i]`. `colors[0]` is ignored,
+    // since block 0 is air.
+    pub fn from_colors(colors: &[[u8; 4]]) -> Self {
+        let mut blocks = vec![Some(BlockDef::air())];
+        blocks.extend(colors.iter().enumerate().skip(1).map(|(block, &color)| {
+            Some(BlockDef {
+                color,
+                ..BlockDef::new(format!("Color {}", block))
+            })
+        }));
+        Self::with_blocks(blocks)
+    }
+
+    // The colors of blocks 0 to 255, for formats with a fixed-size palette.
+    pub fn palette(&self) -> [[u8; 4]; 256] {
+        let mut palette = [[0; 4]; 256];
+        for (block, color) in palette.iter_mut().enumerate() {
+            *color = self.get(block as super::Block).color
+        }
+        palette
+    }
+
+    // The texture array layer for a face of `block`, 0 if it has no texture.
+    pub fn layer(&self, block: super::Block, direction: usize) -> u32 {
+        self.layers
+            .get(block as usize)
+            .map_or(0, |layers| layers[direction])
+    }
+
+    // The texture file names, in layer order starting from layer 1.
+    pub fn textures(&self) -> &[String] {
+        &self.textures
+    }
+
+    pub fn texture_path(&self, name: &str) -> std::path::PathBuf {
+        self.texture_dir.join(name)
+    }
+
+    pub fn get(&self, block: super::Block) -> &BlockDef {
+        match self.blocks.get(block as usize) {
+            Some(Some(def)) => def,
+            _ => &self.missing,
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<super::Block> {
+        self.iter()
+            .find(|(_, def)| def.name == name)
+            .map(|(block, _)| block)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (super::Block, &BlockDef)> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(block, def)| Some((block as super::Block, def.as_ref()?)))
+    }
+
+    // Whether the face of `block` that touches `neighbor` can be seen.
+    pub fn face_visible(&self, block: super::Block, neighbor: super::Block) -> bool {
+        if block == Self::AIR {
+            return false;
+        }
+        let neighbor_def = self.get(neighbor);
+        !neighbor_def.opaque && (block != neighbor || !neighbor_def.transparent)
+    }
+}
+
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+// Accepts `rrggbb` or `rrggbbaa`, with an optional leading `#`.
+fn parse_color(value: &str) -> Option<[u8; 4]> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return None;
+    }
+    let mut color = [0xff; 4];
+    for (i, channel) in color.iter_mut().enumerate().take(hex.len() / 2) {
+        *channel = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(color)
+}
+
+#[derive(Debug)]
+pub enum RegistryError {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl std::fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read block registry: {}", e),
+            Self::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+impl From<std::io::Error> for RegistryError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_textures_override_texture_in_any_order() {
+        let registry = BlockRegistry::parse(
+            "[1]\ntexture_top = t\ntexture = a\n\n\
+             [2]\ntexture = a\ntexture_side = s\ntexture_bottom = b\n",
+        )
+        .unwrap();
+
+        let textures = |block| {
+            registry
+                .get(block)
+                .textures
+                .iter()
+                .map(|texture| texture.as_deref().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(textures(1), ["a", "a", "a", "a", "t", "a"]);
+        assert_eq!(textures(2), ["s", "s", "s", "s", "a", "b"]);
+    }
+}
@@ -1,6 +1,7 @@
-#[derive(Default)]
 pub struct Chunk {
     blocks: Box<[[[super::Block; Self::SIZE]; Self::SIZE]; Self::SIZE]>,
+    light: Box<[[[u8; Self::SIZE]; Self::SIZE]; Self::SIZE]>, // block light (4) | sky light (4)
+    dirty: bool,
 }
 
 impl Chunk {
@@ -8,6 +9,9 @@ impl Chunk {
     pub const SIZE2: usize = Self::SIZE * Self::SIZE;
     pub const SIZE3: usize = Self::SIZE * Self::SIZE * Self::SIZE;
 
+    // The light of open sky, where no chunk is stored.
+    pub const SKY_LIGHT: u8 = 0xf0;
+
     pub fn test1() -> Self {
         let mut chunk = Self::default();
         for x in 0..Self::SIZE {
@@ -38,60 +42,117 @@ impl Chunk {
         chunk
     }
 
-    fn face(x: usize, y: usize, z: usize, f: usize) -> u32 {
-        (x << 0 | y << 5 | z << 10 | f << 15) as u32
+    pub fn faces(
+        &self,
+        registry: &super::BlockRegistry,
+        strategy: super::MeshStrategy,
+    ) -> Vec<super::Face> {
+        super::mesh::mesh(
+            strategy,
+            registry,
+            |[x, y, z]| self.get2(x as usize, y as usize, z as usize),
+            |[x, y, z]| self.light(x as usize, y as usize, z as usize),
+        )
     }
 
-    pub fn faces(&self) -> Vec<u32> {
-        let mut faces = Vec::new();
-        for x in 0..Self::SIZE {
-            for y in 0..Self::SIZE {
-                for z in 0..Self::SIZE {
-                    if self.get2(x, y, z) {
-                        if !self.get2(x + 1, y, z) {
-                            faces.push(Self::face(x, y, z, 0))
-                        }
-                        if !self.get2(x - 1, y, z) {
-                            faces.push(Self::face(x, y, z, 1))
-                        }
-                        if !self.get2(x, y + 1, z) {
-                            faces.push(Self::face(x, y, z, 2))
-                        }
-                        if !self.get2(x, y - 1, z) {
-                            faces.push(Self::face(x, y, z, 3))
-                        }
-                        if !self.get2(x, y, z + 1) {
-                            faces.push(Self::face(x, y, z, 4))
-                        }
-                        if !self.get2(x, y, z - 1) {
-                            faces.push(Self::face(x, y, z, 5))
-                        }
-                    }
-                }
-            }
+    // Casts a ray in chunk-local voxel coordinates and returns the first solid block it hits.
+    pub fn raycast(
+        &self,
+        ray: &super::Ray,
+        max_distance: f32,
+        registry: &super::BlockRegistry,
+    ) -> Option<super::RayHit> {
+        super::raycast::cast(ray, max_distance, |[x, y, z]| {
+            registry.get(self.get2(x as usize, y as usize, z as usize)).solid
+        })
+    }
+
+    pub fn get2(&self, x: usize, y: usize, z: usize) -> super::Block {
+        self.get(x, y, z).unwrap_or(super::BlockRegistry::AIR)
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Option<super::Block> {
+        Some(*self.blocks.get(x)?.get(y)?.get(z)?)
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, block: super::Block) {
+        if self.blocks[x][y][z] != block {
+            self.blocks[x][y][z] = block;
+            self.dirty = true
         }
+    }
+
+    // The light at a voxel, or `SKY_LIGHT` outside the chunk.
+    pub fn light(&self, x: usize, y: usize, z: usize) -> u8 {
+        let light = || Some(*self.light.get(x)?.get(y)?.get(z)?);
+        light().unwrap_or(Self::SKY_LIGHT)
+    }
 
-        faces
+    // Light doesn't affect `dirty`, since `Object` tracks which meshes it changes.
+    pub fn set_light(&mut self, x: usize, y: usize, z: usize, light: u8) {
+        self.light[x][y][z] = light
     }
 
-    pub fn get2(&self, x: usize, y: usize, z: usize) -> bool {
-        self.get(x, y, z).unwrap_or(false)
+    pub fn clear_light(&mut self) {
+        *self.light = Default::default()
     }
 
-    pub fn get(&self, x: usize, y: usize, z: usize) -> Option<bool> {
-        Some(*self.blocks.get(x)?.get(y)?.get(z)? != 0)
+    // Whether the chunk changed since its mesh was last built.
+    pub fn dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true
+    }
+
+    pub fn clear_dirty(&mut self) {
+        self.dirty = false
+    }
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self {
+            blocks: Default::default(),
+            light: Default::default(),
+            dirty: true,
+        }
     }
 }
 
-#[derive(Copy, Clone, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
 pub struct Coord {
-    pub x: usize,
-    pub y: usize,
-    pub z: usize,
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
 }
 
 impl Coord {
-    pub fn new(x: usize, y: usize, z: usize) -> Self {
+    const SIZE: i32 = Chunk::SIZE as i32;
+
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
         Self { x, y, z }
     }
+
+    pub fn offset(self, [x, y, z]: [i32; 3]) -> Self {
+        Self::new(self.x + x, self.y + y, self.z + z)
+    }
+
+    // Splits a global voxel coordinate into the chunk containing it and the position within
+    // that chunk.
+    pub fn split(voxel: [i32; 3]) -> (Self, [usize; 3]) {
+        let [x, y, z] = voxel.map(|v| v.div_euclid(Self::SIZE));
+        (Self::new(x, y, z), voxel.map(|v| v.rem_euclid(Self::SIZE) as usize))
+    }
+
+    // The global voxel coordinate of the chunk's first voxel.
+    pub fn origin(self) -> [i32; 3] {
+        [self.x * Self::SIZE, self.y * Self::SIZE, self.z * Self::SIZE]
+    }
+
+    pub fn voxel(self, [x, y, z]: [usize; 3]) -> [i32; 3] {
+        let [ox, oy, oz] = self.origin();
+        [ox + x as i32, oy + y as i32, oz + z as i32]
+    }
 }
@@ -0,0 +1,395 @@
+// Native object file format, all integers little-endian:
+//
+//   header       magic "VXLT", version: u16, chunk size: u16, chunk count: u32
+//   chunk table  per chunk: x, y, z: i32, data offset: u32, data length: u32
+//   chunk data   per chunk: runs of (length: u16, block: u16) in x, y, z order
+//
+// Data offsets are relative to the end of the chunk table.
+//
+// A single chunk is saved the same way, without the table:
+//
+//   header       magic "VXCK", version: u16, chunk size: u16, data length: u32
+//   chunk data   runs as above
+
+use std::convert::TryInto;
+
+const MAGIC: [u8; 4] = *b"VXLT";
+const CHUNK_MAGIC: [u8; 4] = *b"VXCK";
+const VERSION: u16 = 1;
+const HEADER_SIZE: usize = 12;
+const TABLE_ENTRY_SIZE: usize = 20;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(std::io::Error),
+    Truncated,
+    BadMagic,
+    UnsupportedVersion(u16),
+    Corrupt(String),
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read object: {}", e),
+            Self::Truncated => write!(f, "object file is truncated"),
+            Self::BadMagic => write!(f, "not an object file"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "object file version {} is not supported (expected {})",
+                version, VERSION
+            ),
+            Self::Corrupt(message) => write!(f, "object file is corrupt: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<std::io::Error> for LoadError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl super::Object {
+    pub fn save<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        // Sorted, so the same object always saves to the same bytes.
+        let mut chunks = self.chunks().collect::<Vec<_>>();
+        chunks.sort_unstable_by_key(|(coord, _)| (coord.x, coord.y, coord.z));
+
+        let mut table = Vec::with_capacity(chunks.len() * TABLE_ENTRY_SIZE);
+        let mut data = Vec::new();
+        for (coord, chunk) in &chunks {
+            let offset = data.len();
+            encode_chunk(chunk, &mut data);
+            table.extend_from_slice(&coord.x.to_le_bytes());
+            table.extend_from_slice(&coord.y.to_le_bytes());
+            table.extend_from_slice(&coord.z.to_le_bytes());
+            table.extend_from_slice(&(offset as u32).to_le_bytes());
+            table.extend_from_slice(&((data.len() - offset) as u32).to_le_bytes());
+        }
+
+        write_header(&mut writer, MAGIC)?;
+        writer.write_all(&(chunks.len() as u32).to_le_bytes())?;
+        writer.write_all(&table)?;
+        writer.write_all(&data)
+    }
+
+    pub fn load<R: std::io::Read>(mut reader: R) -> Result<Self, LoadError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut header = Reader::new(&bytes);
+        read_header(&mut header, MAGIC)?;
+        let chunk_count = header.u32()? as usize;
+
+        let data_start = chunk_count
+            .checked_mul(TABLE_ENTRY_SIZE)
+            .and_then(|size| size.checked_add(HEADER_SIZE))
+            .ok_or(LoadError::Truncated)?;
+        let data = bytes.get(data_start..).ok_or(LoadError::Truncated)?;
+
+        let mut object = Self::new();
+        for _ in 0..chunk_count {
+            let coord = super::ChunkCoord::new(header.i32()?, header.i32()?, header.i32()?);
+            let offset = header.u32()? as usize;
+            let length = header.u32()? as usize;
+            let chunk_data = offset
+                .checked_add(length)
+                .and_then(|end| data.get(offset..end))
+                .ok_or(LoadError::Truncated)?;
+            if object.chunk(coord).is_some() {
+                return Err(LoadError::Corrupt(format!("duplicate chunk {:?}", coord)));
+            }
+            object.insert_chunk(coord, decode_chunk(chunk_data)?);
+        }
+
+        Ok(object)
+    }
+
+    pub fn save_file<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.save(&mut writer)?;
+        std::io::Write::flush(&mut writer)
+    }
+
+    pub fn load_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self, LoadError> {
+        Self::load(std::fs::File::open(path)?)
+    }
+}
+
+impl super::Chunk {
+    pub fn save<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let mut data = Vec::new();
+        encode_chunk(self, &mut data);
+        write_header(&mut writer, CHUNK_MAGIC)?;
+        writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        writer.write_all(&data)
+    }
+
+    pub fn load<R: std::io::Read>(mut reader: R) -> Result<Self, LoadError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut reader = Reader::new(&bytes);
+        read_header(&mut reader, CHUNK_MAGIC)?;
+        let length = reader.u32()? as usize;
+        let chunk = decode_chunk(reader.bytes(length)?)?;
+        if !reader.is_empty() {
+            return Err(LoadError::Corrupt("trailing data after chunk".to_string()));
+        }
+        Ok(chunk)
+    }
+}
+
+fn write_header<W: std::io::Write>(writer: &mut W, magic: [u8; 4]) -> std::io::Result<()> {
+    writer.write_all(&magic)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&(super::Chunk::SIZE as u16).to_le_bytes())
+}
+
+fn read_header(reader: &mut Reader, magic: [u8; 4]) -> Result<(), LoadError> {
+    if reader.bytes(4)? != magic {
+        return Err(LoadError::BadMagic);
+    }
+    let version = reader.u16()?;
+    if version != VERSION {
+        return Err(LoadError::UnsupportedVersion(version));
+    }
+    let chunk_size = reader.u16()?;
+    if chunk_size as usize != super::Chunk::SIZE {
+        return Err(LoadError::Corrupt(format!(
+            "unsupported chunk size {}",
+            chunk_size
+        )));
+    }
+    Ok(())
+}
+
+fn encode_chunk(chunk: &super::Chunk, out: &mut Vec<u8>) {
+    let mut run: Option<(u16, super::Block)> = None;
+    let mut flush = |(length, block): (u16, super::Block)| {
+        out.extend_from_slice(&length.to_le_bytes());
+        out.extend_from_slice(&block.to_le_bytes());
+    };
+
+    for x in 0..super::Chunk::SIZE {
+        for y in 0..super::Chunk::SIZE {
+            for z in 0..super::Chunk::SIZE {
+                let block = chunk.get2(x, y, z);
+                run = match run {
+                    Some((length, current)) if current == block && length < u16::MAX => {
+                        Some((length + 1, current))
+                    }
+                    Some(previous) => {
+                        flush(previous);
+                        Some((1, block))
+                    }
+                    None => Some((1, block)),
+                }
+            }
+        }
+    }
+    flush(run.unwrap());
+}
+
+fn decode_chunk(data: &[u8]) -> Result<super::Chunk, LoadError> {
+    let mut reader = Reader::new(data);
+    let mut chunk = super::Chunk::default();
+    let mut index = 0;
+
+    while !reader.is_empty() {
+        let length = reader.u16()? as usize;
+        let block = reader.u16()?;
+        if length == 0 || index + length > super::Chunk::SIZE3 {
+            return Err(LoadError::Corrupt("invalid run length".to_string()));
+        }
+        for index in index..index + length {
+            let x = index / super::Chunk::SIZE2;
+            let y = index / super::Chunk::SIZE % super::Chunk::SIZE;
+            let z = index % super::Chunk::SIZE;
+            chunk.set(x, y, z, block)
+        }
+        index += length;
+    }
+
+    if index != super::Chunk::SIZE3 {
+        return Err(LoadError::Corrupt("chunk data is incomplete".to_string()));
+    }
+    Ok(chunk)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], LoadError> {
+        if self.data.len() < count {
+            return Err(LoadError::Truncated);
+        }
+        let (bytes, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, LoadError> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, LoadError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, LoadError> {
+        Ok(i32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::{Chunk, ChunkCoord, Object};
+
+    fn saved_chunk() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        Chunk::test1().save(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn same_blocks(a: &Chunk, b: &Chunk) -> bool {
+        (0..Chunk::SIZE3).all(|index| {
+            let (x, y, z) = (
+                index / Chunk::SIZE2,
+                index / Chunk::SIZE % Chunk::SIZE,
+                index % Chunk::SIZE,
+            );
+            a.get2(x, y, z) == b.get2(x, y, z)
+        })
+    }
+
+    #[test]
+    fn chunk_round_trip() {
+        let chunk = Chunk::load(&saved_chunk()[..]).unwrap();
+        assert!(same_blocks(&chunk, &Chunk::test1()));
+    }
+
+    #[test]
+    fn object_round_trip() {
+        let mut object = Object::new();
+        object.set_block([-40, 3, 70], 5);
+        object.set_block([0, 0, 0], 1);
+        object.set_block([31, 31, 31], 2);
+        let mut bytes = Vec::new();
+        object.save(&mut bytes).unwrap();
+
+        let loaded = Object::load(&bytes[..]).unwrap();
+        assert_eq!(loaded.chunks().count(), object.chunks().count());
+        for (coord, chunk) in object.chunks() {
+            assert!(same_blocks(loaded.chunk(coord).unwrap(), chunk));
+        }
+        assert_eq!(loaded.get_block([-40, 3, 70]), 5);
+        assert_eq!(loaded.get_block([31, 31, 31]), 2);
+        assert_eq!(loaded.get_block([1, 0, 0]), 0);
+    }
+
+    #[test]
+    fn object_saves_are_identical() {
+        let save = |object: &Object| {
+            let mut bytes = Vec::new();
+            object.save(&mut bytes).unwrap();
+            bytes
+        };
+        let object = || {
+            let mut object = Object::new();
+            for i in -4..4 {
+                object.set_block([i * 32, i * -64, i * 96], (i + 5) as u16);
+            }
+            object
+        };
+        let bytes = save(&object());
+        assert_eq!(bytes, save(&object()));
+        assert_eq!(bytes, save(&Object::load(&bytes[..]).unwrap()));
+    }
+
+    #[test]
+    fn bad_magic() {
+        let mut bytes = saved_chunk();
+        bytes[0] = b'X';
+        assert!(matches!(Chunk::load(&bytes[..]), Err(LoadError::BadMagic)));
+
+        // A chunk file isn't an object file and vice versa.
+        assert!(matches!(
+            Object::load(&saved_chunk()[..]),
+            Err(LoadError::BadMagic)
+        ));
+        let mut bytes = Vec::new();
+        Object::new().save(&mut bytes).unwrap();
+        assert!(matches!(Chunk::load(&bytes[..]), Err(LoadError::BadMagic)));
+    }
+
+    #[test]
+    fn version_mismatch() {
+        let mut bytes = saved_chunk();
+        bytes[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            Chunk::load(&bytes[..]),
+            Err(LoadError::UnsupportedVersion(version)) if version == VERSION + 1
+        ));
+
+        let mut bytes = Vec::new();
+        Object::new().save(&mut bytes).unwrap();
+        bytes[4..6].copy_from_slice(&0u16.to_le_bytes());
+        assert!(matches!(
+            Object::load(&bytes[..]),
+            Err(LoadError::UnsupportedVersion(0))
+        ));
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = saved_chunk();
+        for length in [0, 3, 7, 11, bytes.len() - 1] {
+            assert!(
+                matches!(Chunk::load(&bytes[..length]), Err(LoadError::Truncated)),
+                "chunk cut to {} bytes",
+                length
+            );
+        }
+
+        let mut object = Object::new();
+        object.insert_chunk(ChunkCoord::new(1, -2, 3), Chunk::test1());
+        let mut bytes = Vec::new();
+        object.save(&mut bytes).unwrap();
+        for length in [10, HEADER_SIZE + 4, bytes.len() - 1] {
+            assert!(
+                matches!(Object::load(&bytes[..length]), Err(LoadError::Truncated)),
+                "object cut to {} bytes",
+                length
+            );
+        }
+    }
+
+    #[test]
+    fn corrupt_runs() {
+        // One run covering a single voxel too many.
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, CHUNK_MAGIC).unwrap();
+        bytes.extend_from_slice(&8u32.to_le_bytes());
+        for length in [u16::MAX, 2] {
+            bytes.extend_from_slice(&length.to_le_bytes());
+            bytes.extend_from_slice(&1u16.to_le_bytes());
+        }
+        assert!(matches!(
+            Chunk::load(&bytes[..]),
+            Err(LoadError::Corrupt(_))
+        ));
+    }
+}
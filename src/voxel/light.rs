@@ -0,0 +1,341 @@
+// Flood-fill lighting, the way block games do it. Every stored voxel has two light levels from 0 to
+// 15 (see `Chunk::light`): block light, spread from emissive blocks, and sky light, which shines
+// straight down from open sky without fading and spreads sideways like block light. Light loses
+// one level per step into a neighboring voxel and doesn't enter opaque blocks. It's only stored in
+// existing chunks, and the top of a chunk with no chunk above it is open to the sky.
+
+const MAX_LEVEL: u8 = 15;
+const DOWN: usize = 5; // -z
+
+#[derive(Copy, Clone)]
+enum Channel {
+    Block,
+    Sky,
+}
+
+impl Channel {
+    const ALL: [Self; 2] = [Self::Block, Self::Sky];
+
+    fn get(self, light: u8) -> u8 {
+        match self {
+            Self::Block => light & 0xf,
+            Self::Sky => light >> 4,
+        }
+    }
+
+    fn with(self, light: u8, level: u8) -> u8 {
+        match self {
+            Self::Block => light & 0xf0 | level,
+            Self::Sky => light & 0xf | level << 4,
+        }
+    }
+
+    // The level that light at `level` spreads to the neighbor in `direction`.
+    fn step(self, level: u8, direction: usize) -> u8 {
+        match self {
+            Self::Sky if level == MAX_LEVEL && direction == DOWN => MAX_LEVEL,
+            _ => level.saturating_sub(1),
+        }
+    }
+
+    // The level a voxel has by itself, regardless of its neighbors.
+    fn source(self, def: &super::BlockDef, sees_sky: bool) -> u8 {
+        match self {
+            Self::Block => def.emissive.min(MAX_LEVEL),
+            Self::Sky if sees_sky && !def.opaque => MAX_LEVEL,
+            Self::Sky => 0,
+        }
+    }
+}
+
+fn neighbor(voxel: [i32; 3], direction: usize) -> [i32; 3] {
+    let [nx, ny, nz] = super::direction_normal(direction);
+    [voxel[0] + nx, voxel[1] + ny, voxel[2] + nz]
+}
+
+impl super::Object {
+    // Updates light for the blocks changed since the last call, or for the whole object if chunks
+    // were inserted since. Chunks whose faces are lit differently are marked dirty.
+    pub fn update_light(&mut self, registry: &super::BlockRegistry) {
+        if !self.lit {
+            self.light_updates.clear();
+            self.relight(registry);
+            self.lit = true;
+            return;
+        }
+        let voxels = std::mem::take(&mut self.light_updates);
+        self.relight_voxels(&voxels, registry);
+        for (voxel, light) in std::mem::take(&mut self.light_changes) {
+            if self.get_light(voxel) != light {
+                self.mark_touching_dirty(voxel)
+            }
+        }
+    }
+
+    fn relight(&mut self, registry: &super::BlockRegistry) {
+        const SIZE: usize = super::Chunk::SIZE;
+
+        let coords = self.chunks().map(|(coord, _)| coord).collect::<Vec<_>>();
+        let mut queues = [
+            std::collections::VecDeque::new(),
+            std::collections::VecDeque::new(),
+        ];
+        for coord in coords {
+            let open = self.chunk(coord.offset([0, 0, 1])).is_none();
+            let chunk = self.chunk_mut(coord).unwrap();
+            chunk.clear_light();
+            for x in 0..SIZE {
+                for y in 0..SIZE {
+                    for z in 0..SIZE {
+                        let def = registry.get(chunk.get2(x, y, z));
+                        let mut light = 0;
+                        for (channel, queue) in Channel::ALL.iter().zip(&mut queues) {
+                            let level = channel.source(def, open && z == SIZE - 1);
+                            if level > 0 {
+                                light = channel.with(light, level);
+                                queue.push_back(coord.voxel([x, y, z]))
+                            }
+                        }
+                        chunk.set_light(x, y, z, light)
+                    }
+                }
+            }
+        }
+
+        let [block_queue, sky_queue] = queues;
+        self.spread(Channel::Block, block_queue, registry);
+        self.spread(Channel::Sky, sky_queue, registry);
+        self.mark_all_dirty()
+    }
+
+    // Takes away the light of changed voxels and everything it spread to, then lets light spread
+    // back in from the surrounding voxels and from the voxels' new blocks.
+    fn relight_voxels(&mut self, voxels: &[[i32; 3]], registry: &super::BlockRegistry) {
+        let voxels = voxels
+            .iter()
+            .filter_map(|&voxel| {
+                let (coord, [x, y, z]) = super::ChunkCoord::split(voxel);
+                let def = registry.get(self.chunk(coord)?.get2(x, y, z));
+                let open =
+                    z == super::Chunk::SIZE - 1 && self.chunk(coord.offset([0, 0, 1])).is_none();
+                Some((voxel, def, open))
+            })
+            .collect::<Vec<_>>();
+
+        for &channel in &Channel::ALL {
+            let mut darkened = std::collections::VecDeque::new();
+            for &(voxel, ..) in &voxels {
+                darkened.push_back((voxel, channel.get(self.get_light(voxel))));
+                self.set_level(voxel, channel, 0)
+            }
+            let mut edge = self.unspread(channel, darkened, registry);
+
+            for &(voxel, def, open) in &voxels {
+                let source = channel.source(def, open);
+                if source > 0 {
+                    self.set_level(voxel, channel, source);
+                    edge.push_back(voxel)
+                }
+                // Only stored light spreads back; open sky beside a chunk doesn't light it.
+                if !def.opaque {
+                    edge.extend((0..6).map(|direction| neighbor(voxel, direction)).filter(
+                        |&neighbor| {
+                            let (coord, [x, y, z]) = super::ChunkCoord::split(neighbor);
+                            self.chunk(coord)
+                                .is_some_and(|chunk| channel.get(chunk.light(x, y, z)) > 0)
+                        },
+                    ))
+                }
+            }
+            self.spread(channel, edge, registry)
+        }
+    }
+
+    // Spreads light outwards from the voxels in `queue`.
+    fn spread(
+        &mut self,
+        channel: Channel,
+        mut queue: std::collections::VecDeque<[i32; 3]>,
+        registry: &super::BlockRegistry,
+    ) {
+        while let Some(voxel) = queue.pop_front() {
+            let level = channel.get(self.get_light(voxel));
+            for direction in 0..6 {
+                let next = neighbor(voxel, direction);
+                let next_level = channel.step(level, direction);
+                if next_level > 0 && self.brighten(next, channel, next_level, registry) {
+                    queue.push_back(next)
+                }
+            }
+        }
+    }
+
+    // Darkens every voxel that could have gotten its light from the voxels in `queue`, each given
+    // with the level it had. Returns the voxels bordering the darkened ones that have light of
+    // their own, to spread back into them.
+    fn unspread(
+        &mut self,
+        channel: Channel,
+        mut queue: std::collections::VecDeque<([i32; 3], u8)>,
+        registry: &super::BlockRegistry,
+    ) -> std::collections::VecDeque<[i32; 3]> {
+        let mut edge = std::collections::VecDeque::new();
+        while let Some((voxel, level)) = queue.pop_front() {
+            for direction in 0..6 {
+                let next = neighbor(voxel, direction);
+                let (coord, [x, y, z]) = super::ChunkCoord::split(next);
+                let chunk = match self.chunk(coord) {
+                    Some(chunk) => chunk,
+                    None => continue,
+                };
+                let next_level = channel.get(chunk.light(x, y, z));
+                if next_level == 0 {
+                    continue;
+                }
+                if next_level > channel.step(level, direction) {
+                    edge.push_back(next);
+                    continue;
+                }
+
+                let emissive = channel.source(registry.get(chunk.get2(x, y, z)), false);
+                self.set_level(next, channel, emissive);
+                queue.push_back((next, next_level));
+                if emissive > 0 {
+                    edge.push_back(next)
+                }
+            }
+        }
+        edge
+    }
+
+    // Raises the level of a stored, non-opaque voxel to `level` if it's darker. Returns whether it
+    // was.
+    fn brighten(
+        &mut self,
+        voxel: [i32; 3],
+        channel: Channel,
+        level: u8,
+        registry: &super::BlockRegistry,
+    ) -> bool {
+        let (coord, [x, y, z]) = super::ChunkCoord::split(voxel);
+        match self.chunk(coord) {
+            Some(chunk)
+                if !registry.get(chunk.get2(x, y, z)).opaque
+                    && channel.get(chunk.light(x, y, z)) < level =>
+            {
+                self.set_level(voxel, channel, level);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn set_level(&mut self, voxel: [i32; 3], channel: Channel, level: u8) {
+        let (coord, [x, y, z]) = super::ChunkCoord::split(voxel);
+        if let Some(chunk) = self.chunk_mut(coord) {
+            let light = chunk.light(x, y, z);
+            chunk.set_light(x, y, z, channel.with(light, level));
+            // After a full relight, every chunk is remeshed anyway.
+            if self.lit {
+                self.light_changes.entry(voxel).or_insert(light);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voxel::{Block, BlockRegistry, Chunk, ChunkCoord, Object};
+
+    const STONE: Block = 1;
+    const LAMP: Block = 7;
+    const FAR: ChunkCoord = ChunkCoord { x: 6, y: 0, z: 0 };
+
+    // Stone ground up to z = 15 in chunk (0, 0, 0) with a lamp on it near the +x side, and a chunk
+    // far away from it. Lit, with nothing dirty.
+    fn ground() -> (Object, BlockRegistry) {
+        let registry = BlockRegistry::builtin();
+        let mut object = Object::new();
+        for x in 0..32 {
+            for y in 0..32 {
+                for z in 0..16 {
+                    object.set_block([x, y, z], STONE)
+                }
+            }
+        }
+        object.set_block([30, 5, 16], LAMP);
+        object.set_block(FAR.origin(), STONE);
+        object.update_light(&registry);
+        object.take_dirty();
+        (object, registry)
+    }
+
+    // Updates the light and checks that a full relight agrees with it. Returns the chunks the
+    // update marked dirty.
+    fn update(object: &mut Object, registry: &BlockRegistry) -> Vec<ChunkCoord> {
+        object.update_light(registry);
+        let dirty = object.take_dirty();
+
+        let voxels = object
+            .chunks()
+            .flat_map(|(coord, _)| {
+                (0..Chunk::SIZE3).map(move |index| {
+                    coord.voxel([
+                        index / Chunk::SIZE2,
+                        index / Chunk::SIZE % Chunk::SIZE,
+                        index % Chunk::SIZE,
+                    ])
+                })
+            })
+            .collect::<Vec<_>>();
+        let light = voxels
+            .iter()
+            .map(|&voxel| object.get_light(voxel))
+            .collect::<Vec<_>>();
+        object.lit = false;
+        object.update_light(registry);
+        object.take_dirty();
+        for (&voxel, &light) in voxels.iter().zip(&light) {
+            assert_eq!(light, object.get_light(voxel), "at {:?}", voxel);
+        }
+        dirty
+    }
+
+    #[test]
+    fn new_chunk_above_shades_the_chunk_below() {
+        let (mut object, registry) = ground();
+        object.set_block([5, 5, 40], STONE);
+        let dirty = update(&mut object, &registry);
+        assert!(dirty.contains(&ChunkCoord::new(0, 0, 0)));
+        assert!(dirty.contains(&ChunkCoord::new(0, 0, 1)));
+        assert!(!dirty.contains(&FAR));
+    }
+
+    #[test]
+    fn new_chunk_takes_in_light_from_its_neighbors() {
+        let (mut object, registry) = ground();
+        object.set_block([40, 5, 20], STONE);
+        let dirty = update(&mut object, &registry);
+        assert!(dirty.contains(&ChunkCoord::new(1, 0, 0)));
+        assert!(!dirty.contains(&FAR));
+        assert_eq!(object.get_light([32, 5, 16]) & 0xf, 13);
+    }
+
+    #[test]
+    fn new_chunks_under_other_chunks() {
+        let (mut object, registry) = ground();
+        object.set_block([5, 5, 80], STONE);
+        update(&mut object, &registry);
+        object.set_block([6, 6, 40], LAMP);
+        let dirty = update(&mut object, &registry);
+        assert!(!dirty.contains(&FAR));
+    }
+
+    #[test]
+    fn unchanged_chunks_stay_clean() {
+        let (mut object, registry) = ground();
+        object.set_block(FAR.offset([0, 0, 1]).origin(), STONE);
+        let dirty = update(&mut object, &registry);
+        assert!(!dirty.contains(&ChunkCoord::new(0, 0, 0)));
+    }
+}
@@ -0,0 +1,225 @@
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum MeshStrategy {
+    PerFace,
+    #[default]
+    Greedy,
+}
+
+impl MeshStrategy {
+    pub fn next(self) -> Self {
+        match self {
+            Self::PerFace => Self::Greedy,
+            Self::Greedy => Self::PerFace,
+        }
+    }
+}
+
+impl std::str::FromStr for MeshStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "per-face" => Ok(Self::PerFace),
+            "greedy" => Ok(Self::Greedy),
+            _ => Err(format!("unknown mesh strategy `{}`", s)),
+        }
+    }
+}
+
+// Per-instance data for one quad, matching the vertex inputs of `voxel.vert`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Face {
+    pub data: u32, // x (5) | y (5) | z (5) | direction (3) | width - 1 (5) | height - 1 (5)
+    pub color: u32, // RGBA8
+    pub layer: u32, // texture array layer
+    pub lighting: u32, // AO per corner (2 bits each) | flip (1) | unused (7) | block light (4) | sky light (4)
+}
+
+impl Face {
+    // Set when the quad should be split along its other diagonal, see `ambient_occlusion`.
+    pub const FLIP: u32 = 1 << 8;
+
+    // Where the light level of the voxel in front of the face is stored, see `Chunk::light`.
+    pub const LIGHT_SHIFT: u32 = 16;
+
+    fn new(direction: usize, block: super::Block, registry: &super::BlockRegistry) -> Self {
+        Self {
+            data: (direction << 15) as u32,
+            color: registry.get(block).packed_color(),
+            layer: registry.layer(block, direction),
+            lighting: 0,
+        }
+    }
+
+    // Whether all four corners have the same ambient occlusion, so that the face looks the same
+    // when stretched over a larger quad.
+    fn uniform(self) -> bool {
+        let ao = self.lighting & 0xff;
+        ao == (ao & 0x3) * 0x55
+    }
+
+    fn placed(self, [x, y, z]: [usize; 3], width: usize, height: usize) -> Self {
+        Self {
+            data: self.data
+                | (x | y << 5 | z << 10 | (width - 1) << 18 | (height - 1) << 23) as u32,
+            ..self
+        }
+    }
+}
+
+// For each face direction: the normal axis, then the axes along which the face's width and height
+// extend. Must agree with `faceTransforms` in `voxel.vert`.
+pub const DIRECTION_AXES: [(usize, usize, usize); 6] = [
+    (0, 1, 2), // +x
+    (0, 2, 1), // -x
+    (1, 2, 0), // +y
+    (1, 0, 2), // -y
+    (2, 0, 1), // +z
+    (2, 1, 0), // -z
+];
+
+pub fn direction_normal(direction: usize) -> [i32; 3] {
+    let mut normal = [0; 3];
+    normal[DIRECTION_AXES[direction].0] = if direction % 2 == 0 { 1 } else { -1 };
+    normal
+}
+
+// Classic 4-corner voxel ambient occlusion (see "Ambient occlusion for Minecraft-like worlds" on
+// 0fps.net) for the face of the voxel at `pos` facing `direction`. Corner `i + 2 * j` is at the
+// face's low (0) or high (1) end along its u and v axes, and gets 2 bits from 0 (darkest) to 3
+// (unoccluded). `Face::FLIP` is set when the 00-11 diagonal is brighter than the 10-01 one, since
+// splitting the quad along the brighter diagonal avoids anisotropic streaks.
+pub fn ambient_occlusion<F: Fn([i32; 3]) -> super::Block>(
+    registry: &super::BlockRegistry,
+    get: F,
+    pos: [i32; 3],
+    direction: usize,
+) -> u32 {
+    let (_, u_axis, v_axis) = DIRECTION_AXES[direction];
+    let normal = direction_normal(direction);
+    let occludes = |du: i32, dv: i32| {
+        let mut neighbor = [pos[0] + normal[0], pos[1] + normal[1], pos[2] + normal[2]];
+        neighbor[u_axis] += du;
+        neighbor[v_axis] += dv;
+        registry.get(get(neighbor)).opaque as u32
+    };
+
+    let mut ao = [0; 4];
+    for (corner, ao) in ao.iter_mut().enumerate() {
+        let du = if corner & 1 == 0 { -1 } else { 1 };
+        let dv = if corner & 2 == 0 { -1 } else { 1 };
+        let (side1, side2) = (occludes(du, 0), occludes(0, dv));
+        *ao = if side1 + side2 == 2 {
+            0
+        } else {
+            3 - side1 - side2 - occludes(du, dv)
+        }
+    }
+
+    let flip = if ao[0] + ao[3] > ao[1] + ao[2] {
+        Face::FLIP
+    } else {
+        0
+    };
+    ao[0] | ao[1] << 2 | ao[2] << 4 | ao[3] << 6 | flip
+}
+
+// Builds the faces of a chunk. `get` and `light` are called with coordinates from -1 to
+// `Chunk::SIZE`, so they can see one voxel past every border of the chunk.
+pub(super) fn mesh<F: Fn([i32; 3]) -> super::Block, L: Fn([i32; 3]) -> u8>(
+    strategy: MeshStrategy,
+    registry: &super::BlockRegistry,
+    get: F,
+    light: L,
+) -> Vec<Face> {
+    const SIZE: usize = super::Chunk::SIZE;
+
+    let mut faces = Vec::new();
+    let mut mask = vec![None; SIZE * SIZE];
+
+    for (direction, &(n_axis, u_axis, v_axis)) in DIRECTION_AXES.iter().enumerate() {
+        let normal = direction_normal(direction);
+
+        for n in 0..SIZE {
+            // Collect the visible faces of this slice, indexed by (u, v).
+
+            for (index, slot) in mask.iter_mut().enumerate() {
+                let mut pos = [0; 3];
+                pos[n_axis] = n as i32;
+                pos[u_axis] = (index % SIZE) as i32;
+                pos[v_axis] = (index / SIZE) as i32;
+                let block = get(pos);
+                let front = [pos[0] + normal[0], pos[1] + normal[1], pos[2] + normal[2]];
+                *slot = if registry.face_visible(block, get(front)) {
+                    Some(Face {
+                        lighting: ambient_occlusion(registry, &get, pos, direction)
+                            | (light(front) as u32) << Face::LIGHT_SHIFT,
+                        ..Face::new(direction, block, registry)
+                    })
+                } else {
+                    None
+                };
+            }
+
+            // Emit the collected faces as quads.
+
+            let place = |u: usize, v: usize, width: usize, height: usize, face: Face| {
+                let mut pos = [0; 3];
+                pos[n_axis] = n;
+                pos[u_axis] = u;
+                pos[v_axis] = v;
+                face.placed(pos, width, height)
+            };
+
+            match strategy {
+                MeshStrategy::PerFace => {
+                    faces.extend(mask.iter().enumerate().filter_map(|(index, face)| {
+                        Some(place(index % SIZE, index / SIZE, 1, 1, (*face)?))
+                    }))
+                }
+                MeshStrategy::Greedy => {
+                    for v in 0..SIZE {
+                        let mut u = 0;
+                        while u < SIZE {
+                            let face = match mask[u + v * SIZE] {
+                                Some(face) => face,
+                                None => {
+                                    u += 1;
+                                    continue;
+                                }
+                            };
+
+                            // Faces are merged when their `Face` data is equal rather than just
+                            // their block, since a quad has one color, texture layer, occlusion
+                            // and light level. Equal data means the faces look identical, which
+                            // is all merging needs. Faces with an ambient occlusion gradient
+                            // aren't merged, since it would be stretched over the whole quad.
+                            let (width, height) = if face.uniform() {
+                                let width = (u..SIZE)
+                                    .take_while(|&u| mask[u + v * SIZE] == Some(face))
+                                    .count();
+                                let height = (v..SIZE)
+                                    .take_while(|&v| {
+                                        (u..u + width).all(|u| mask[u + v * SIZE] == Some(face))
+                                    })
+                                    .count();
+                                (width, height)
+                            } else {
+                                (1, 1)
+                            };
+
+                            for row in mask[v * SIZE..].chunks_mut(SIZE).take(height) {
+                                row[u..u + width].fill(None)
+                            }
+                            faces.push(place(u, v, width, height, face));
+                            u += width;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    faces
+}
@@ -1,7 +1,18 @@
+mod block;
 mod chunk;
+mod file;
+mod light;
+mod mesh;
 mod object;
+mod raycast;
+mod vox;
 
+pub use block::{BlockDef, BlockRegistry, RegistryError};
 pub use chunk::{Chunk, Coord as ChunkCoord};
+pub use file::LoadError;
+pub use mesh::{direction_normal, Face, MeshStrategy, DIRECTION_AXES};
 pub use object::Object;
+pub use raycast::{Ray, RayHit};
+pub use vox::{Vox, VoxError};
 
-type Block = u16;
+pub type Block = u16;
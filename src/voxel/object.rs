@@ -1,9 +1,22 @@
 pub struct Object {
     chunks: std::collections::HashMap<super::ChunkCoord, super::Chunk>,
+    pub(super) lit: bool, // whether light has been propagated since chunks were inserted
+    pub(super) light_updates: Vec<[i32; 3]>, // voxels changed since light was last updated
+    // The light voxels had before the update in progress changed it, to remesh only what changed.
+    pub(super) light_changes: std::collections::HashMap<[i32; 3], u8>,
 }
 
 impl Object {
-    pub fn test(n: usize) -> Self {
+    pub fn new() -> Self {
+        Self {
+            chunks: std::collections::HashMap::new(),
+            lit: false,
+            light_updates: Vec::new(),
+            light_changes: std::collections::HashMap::new(),
+        }
+    }
+
+    pub fn test(n: i32) -> Self {
         let mut chunks = std::collections::HashMap::new();
         for x in 0..n {
             for y in 0..n {
@@ -13,6 +26,236 @@ impl Object {
                 }
             }
         }
-        Self { chunks }
+        Self {
+            chunks,
+            ..Self::new()
+        }
+    }
+
+    // Rolling grass hills centered on the origin, `radius` chunks out in each horizontal direction.
+    pub fn hills(radius: i32) -> Self {
+        const SIZE: i32 = super::Chunk::SIZE as i32;
+        let mut object = Self::new();
+        for x in -radius * SIZE..radius * SIZE {
+            for y in -radius * SIZE..radius * SIZE {
+                let height = (8. * (x as f32 / 23.).sin() * (y as f32 / 17.).cos()) as i32;
+                for z in -SIZE..=height {
+                    let block = match height - z {
+                        0 => 3,
+                        1..=3 => 2,
+                        _ => 1,
+                    };
+                    object.set_block([x, y, z], block)
+                }
+            }
+        }
+        object
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = (super::ChunkCoord, &super::Chunk)> {
+        self.chunks.iter().map(|(&coord, chunk)| (coord, chunk))
+    }
+
+    pub fn chunk(&self, coord: super::ChunkCoord) -> Option<&super::Chunk> {
+        self.chunks.get(&coord)
+    }
+
+    pub fn insert_chunk(&mut self, coord: super::ChunkCoord, chunk: super::Chunk) {
+        self.chunks.insert(coord, chunk);
+        self.lit = false
+    }
+
+    pub(super) fn chunk_mut(&mut self, coord: super::ChunkCoord) -> Option<&mut super::Chunk> {
+        self.chunks.get_mut(&coord)
+    }
+
+    pub fn get_block(&self, voxel: [i32; 3]) -> super::Block {
+        let (coord, [x, y, z]) = super::ChunkCoord::split(voxel);
+        self.chunk(coord)
+            .map_or(super::BlockRegistry::AIR, |chunk| chunk.get2(x, y, z))
+    }
+
+    // The light at a voxel, as of the last `update_light`. See `Chunk::light`.
+    pub fn get_light(&self, voxel: [i32; 3]) -> u8 {
+        let (coord, [x, y, z]) = super::ChunkCoord::split(voxel);
+        self.chunk(coord)
+            .map_or(super::Chunk::SKY_LIGHT, |chunk| chunk.light(x, y, z))
+    }
+
+    // Casts a ray in global voxel coordinates and returns the first solid block it hits.
+    pub fn raycast(
+        &self,
+        ray: &super::Ray,
+        max_distance: f32,
+        registry: &super::BlockRegistry,
+    ) -> Option<super::RayHit> {
+        super::raycast::cast(ray, max_distance, |voxel| {
+            registry.get(self.get_block(voxel)).solid
+        })
+    }
+
+    // Like `raycast`, but stops at the first face the mesher would emit, so the hit (and its block)
+    // is what the renderer draws at that point. The voxel the ray starts in is never hit.
+    pub fn raycast_visible(
+        &self,
+        ray: &super::Ray,
+        max_distance: f32,
+        registry: &super::BlockRegistry,
+    ) -> Option<(super::RayHit, super::Block)> {
+        let mut previous = None;
+        let hit = super::raycast::cast(ray, max_distance, |voxel| {
+            let block = self.get_block(voxel);
+            let visible = previous.is_some_and(|previous| registry.face_visible(block, previous));
+            previous = Some(block);
+            visible
+        })?;
+        Some((hit, self.get_block(hit.voxel)))
+    }
+
+    // The smallest and largest voxel coordinates covered by any chunk, or `None` if there are no
+    // chunks.
+    pub fn bounds(&self) -> Option<([i32; 3], [i32; 3])> {
+        let size = super::Chunk::SIZE as i32;
+        self.chunks.keys().fold(None, |bounds, coord| {
+            let min = coord.origin();
+            let max = min.map(|v| v + size - 1);
+            Some(match bounds {
+                None => (min, max),
+                Some((lo, hi)) => (
+                    [0, 1, 2].map(|i| std::cmp::min(lo[i], min[i])),
+                    [0, 1, 2].map(|i| std::cmp::max(hi[i], max[i])),
+                ),
+            })
+        })
+    }
+
+    // Creates the containing chunk if it doesn't exist yet. Neighboring chunks that touch the
+    // changed voxel, even just at an edge or corner, are marked dirty too, since their culling and
+    // ambient occlusion depend on it. Light isn't updated until `update_light` is called.
+    pub fn set_block(&mut self, voxel: [i32; 3], block: super::Block) {
+        let (coord, local) = super::ChunkCoord::split(voxel);
+        let chunk = match self.chunks.entry(coord) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                // Every voxel of a new chunk gets light of its own, and the top of the chunk below
+                // it is no longer open to the sky.
+                if self.lit {
+                    const SIZE: usize = super::Chunk::SIZE;
+                    let below = coord.offset([0, 0, -1]);
+                    self.light_updates.extend((0..SIZE * SIZE).flat_map(|xy| {
+                        let [x, y] = [xy / SIZE, xy % SIZE];
+                        (0..SIZE)
+                            .map(move |z| coord.voxel([x, y, z]))
+                            .chain(std::iter::once(below.voxel([x, y, SIZE - 1])))
+                    }))
+                }
+                entry.insert(Default::default())
+            }
+        };
+        if chunk.get2(local[0], local[1], local[2]) == block {
+            return;
+        }
+        chunk.set(local[0], local[1], local[2], block);
+
+        if self.lit {
+            self.light_updates.push(voxel)
+        }
+        self.mark_touching_dirty(voxel)
+    }
+
+    // Marks the chunk containing `voxel` dirty, along with every neighbor that touches it.
+    pub(super) fn mark_touching_dirty(&mut self, voxel: [i32; 3]) {
+        let (coord, local) = super::ChunkCoord::split(voxel);
+        let range = |local: usize| {
+            let low = if local == 0 { -1 } else { 0 };
+            let high = if local == super::Chunk::SIZE - 1 { 1 } else { 0 };
+            low..=high
+        };
+        for x in range(local[0]) {
+            for y in range(local[1]) {
+                for z in range(local[2]) {
+                    if let Some(neighbor) = self.chunks.get_mut(&coord.offset([x, y, z])) {
+                        neighbor.mark_dirty()
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn mark_all_dirty(&mut self) {
+        self.chunks.values_mut().for_each(super::Chunk::mark_dirty)
+    }
+
+    // Returns the chunks that need to be remeshed and clears their dirty flags.
+    pub fn take_dirty(&mut self) -> Vec<super::ChunkCoord> {
+        self.chunks
+            .iter_mut()
+            .filter(|(_, chunk)| chunk.dirty())
+            .map(|(&coord, chunk)| {
+                chunk.clear_dirty();
+                coord
+            })
+            .collect()
+    }
+
+    // Meshes one chunk, culling faces and computing ambient occlusion against the blocks of the
+    // neighboring chunks.
+    pub fn faces(
+        &self,
+        coord: super::ChunkCoord,
+        registry: &super::BlockRegistry,
+        strategy: super::MeshStrategy,
+    ) -> Option<Vec<super::Face>> {
+        const SIZE: i32 = super::Chunk::SIZE as i32;
+
+        let chunk = self.chunk(coord)?;
+        // Indexed by (x + 1) * 9 + (y + 1) * 3 + (z + 1) for a chunk offset of (x, y, z).
+        let mut neighbors = [None; 27];
+        for (index, neighbor) in neighbors.iter_mut().enumerate() {
+            let offset = [index as i32 / 9 - 1, index as i32 / 3 % 3 - 1, index as i32 % 3 - 1];
+            *neighbor = self.chunk(coord.offset(offset));
+        }
+
+        let local = |pos: [i32; 3]| {
+            let [x, y, z] = pos.map(|p| p.rem_euclid(SIZE) as usize);
+            let [cx, cy, cz] = pos.map(|p| p.div_euclid(SIZE));
+            let chunk = if [cx, cy, cz] == [0; 3] {
+                Some(chunk)
+            } else {
+                neighbors[((cx + 1) * 9 + (cy + 1) * 3 + (cz + 1)) as usize]
+            };
+            (chunk, [x, y, z])
+        };
+        Some(super::mesh::mesh(
+            strategy,
+            registry,
+            |pos| match local(pos) {
+                (Some(chunk), [x, y, z]) => chunk.get2(x, y, z),
+                (None, _) => super::BlockRegistry::AIR,
+            },
+            |pos| match local(pos) {
+                (Some(chunk), [x, y, z]) => chunk.light(x, y, z),
+                (None, _) => super::Chunk::SKY_LIGHT,
+            },
+        ))
+    }
+
+    // The `Face::lighting` the mesher computes for the face of `voxel` facing `direction`.
+    pub fn face_lighting(
+        &self,
+        voxel: [i32; 3],
+        direction: usize,
+        registry: &super::BlockRegistry,
+    ) -> u32 {
+        let [x, y, z] = voxel;
+        let [nx, ny, nz] = super::direction_normal(direction);
+        super::mesh::ambient_occlusion(registry, |pos| self.get_block(pos), voxel, direction)
+            | (self.get_light([x + nx, y + ny, z + nz]) as u32) << super::Face::LIGHT_SHIFT
+    }
+}
+
+impl Default for Object {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -0,0 +1,203 @@
+#[derive(Copy, Clone, Debug)]
+pub struct Ray {
+    pub origin: ultraviolet::Vec3,
+    pub direction: ultraviolet::Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: ultraviolet::Vec3, direction: ultraviolet::Vec3) -> Self {
+        Self {
+            origin,
+            direction: direction.normalized(),
+        }
+    }
+
+    pub fn at(&self, distance: f32) -> ultraviolet::Vec3 {
+        self.origin + self.direction * distance
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit {
+    pub voxel: [i32; 3],
+    pub normal: [i32; 3], // zero if the ray starts inside the hit voxel
+    pub distance: f32,
+}
+
+impl RayHit {
+    // The empty voxel in front of the hit face, where a block would be placed.
+    pub fn adjacent(&self) -> [i32; 3] {
+        [
+            self.voxel[0] + self.normal[0],
+            self.voxel[1] + self.normal[1],
+            self.voxel[2] + self.normal[2],
+        ]
+    }
+}
+
+// Walks the voxels along the ray in order (Amanatides & Woo, "A Fast Voxel Traversal Algorithm for
+// Ray Tracing") and returns the first one for which `hit` is true. Voxel `v` spans from `v - 0.5`
+// to `v + 0.5` on each axis, like the cubes drawn by `voxel.vert`. `max_distance` must be finite,
+// since an object has no bounds for the walk to leave. Rays with a zero or non-finite direction,
+// such as one normalized from a zero vector, hit nothing.
+pub(super) fn cast<F: FnMut([i32; 3]) -> bool>(
+    ray: &Ray,
+    max_distance: f32,
+    mut hit: F,
+) -> Option<RayHit> {
+    let origin = ray.origin + ultraviolet::Vec3::broadcast(0.5);
+    let origin = [origin.x, origin.y, origin.z];
+    let direction = [ray.direction.x, ray.direction.y, ray.direction.z];
+    if !origin.iter().chain(&direction).all(|x| x.is_finite()) || direction == [0.; 3] {
+        return None;
+    }
+
+    let mut voxel = origin.map(|o| o.floor() as i32);
+    let mut step = [0; 3];
+    let mut t_max = [f32::INFINITY; 3];
+    let mut t_delta = [f32::INFINITY; 3];
+    for axis in 0..3 {
+        if direction[axis] > 0. {
+            step[axis] = 1;
+            t_max[axis] = (voxel[axis] as f32 + 1. - origin[axis]) / direction[axis];
+            t_delta[axis] = 1. / direction[axis];
+        } else if direction[axis] < 0. {
+            step[axis] = -1;
+            t_max[axis] = (voxel[axis] as f32 - origin[axis]) / direction[axis];
+            t_delta[axis] = -1. / direction[axis];
+        }
+    }
+
+    if hit(voxel) {
+        return Some(RayHit {
+            voxel,
+            normal: [0; 3],
+            distance: 0.,
+        });
+    }
+
+    loop {
+        let axis = (0..3)
+            .min_by(|&a, &b| t_max[a].total_cmp(&t_max[b]))
+            .unwrap();
+        let distance = t_max[axis];
+        if !distance.is_finite() || distance > max_distance {
+            return None;
+        }
+
+        voxel[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+
+        if hit(voxel) {
+            let mut normal = [0; 3];
+            normal[axis] = -step[axis];
+            return Some(RayHit {
+                voxel,
+                normal,
+                distance,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cast_at(ray: &Ray, max_distance: f32, solid: &[[i32; 3]]) -> Option<RayHit> {
+        cast(ray, max_distance, |voxel| solid.contains(&voxel))
+    }
+
+    fn assert_hit(hit: Option<RayHit>, voxel: [i32; 3], normal: [i32; 3], distance: f32) {
+        let hit = hit.expect("ray should hit");
+        assert_eq!(hit.voxel, voxel);
+        assert_eq!(hit.normal, normal);
+        assert!(
+            (hit.distance - distance).abs() < 1e-4,
+            "distance {} != {}",
+            hit.distance,
+            distance
+        );
+    }
+
+    #[test]
+    fn axis_aligned() {
+        let ray = Ray::new(ultraviolet::Vec3::zero(), ultraviolet::Vec3::unit_x());
+        let hit = cast_at(&ray, 100., &[[5, 0, 0], [7, 0, 0]]);
+        assert_hit(hit, [5, 0, 0], [-1, 0, 0], 4.5);
+        assert_eq!(hit.unwrap().adjacent(), [4, 0, 0]);
+    }
+
+    #[test]
+    fn negative_direction() {
+        let ray = Ray::new(
+            ultraviolet::Vec3::new(0., 0.25, 0.),
+            -ultraviolet::Vec3::unit_y(),
+        );
+        assert_hit(
+            cast_at(&ray, 100., &[[0, -3, 0]]),
+            [0, -3, 0],
+            [0, 1, 0],
+            2.75,
+        );
+
+        let ray = Ray::new(ultraviolet::Vec3::zero(), -ultraviolet::Vec3::unit_z());
+        assert_hit(
+            cast_at(&ray, 100., &[[0, 0, 2], [0, 0, -2]]),
+            [0, 0, -2],
+            [0, 0, 1],
+            1.5,
+        );
+    }
+
+    #[test]
+    fn diagonal() {
+        // Along y = x / 2, the ray passes over [2, 0, 0] and enters [1, 1, 0] from below at x = 1,
+        // then [3, 1, 0] from the side at x = 2.5.
+        let direction = ultraviolet::Vec3::new(1., 0.5, 0.);
+        let ray = Ray::new(ultraviolet::Vec3::zero(), direction);
+        let length = direction.mag();
+        assert_hit(
+            cast_at(&ray, 100., &[[2, 0, 0], [3, 1, 0]]),
+            [3, 1, 0],
+            [-1, 0, 0],
+            2.5 * length,
+        );
+        assert_hit(
+            cast_at(&ray, 100., &[[1, 1, 0]]),
+            [1, 1, 0],
+            [0, -1, 0],
+            length,
+        );
+    }
+
+    #[test]
+    fn starts_inside() {
+        let ray = Ray::new(
+            ultraviolet::Vec3::new(3.2, -1.1, 0.4),
+            ultraviolet::Vec3::new(1., 1., 1.),
+        );
+        assert_hit(cast_at(&ray, 100., &[[3, -1, 0]]), [3, -1, 0], [0; 3], 0.);
+    }
+
+    #[test]
+    fn max_distance() {
+        let ray = Ray::new(ultraviolet::Vec3::zero(), ultraviolet::Vec3::unit_x());
+        assert_eq!(cast_at(&ray, 4., &[[5, 0, 0]]), None);
+        assert_hit(cast_at(&ray, 4.5, &[[5, 0, 0]]), [5, 0, 0], [-1, 0, 0], 4.5);
+        assert_eq!(cast_at(&ray, 100., &[]), None);
+    }
+
+    #[test]
+    fn degenerate() {
+        let everything = |_| true;
+        let ray = Ray::new(ultraviolet::Vec3::zero(), ultraviolet::Vec3::zero());
+        assert_eq!(cast(&ray, 100., everything), None);
+
+        let ray = Ray {
+            origin: ultraviolet::Vec3::zero(),
+            direction: ultraviolet::Vec3::new(f32::NAN, 0., 1.),
+        };
+        assert_eq!(cast(&ray, 100., everything), None);
+    }
+}
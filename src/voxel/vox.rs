@@ -0,0 +1,760 @@
+// MagicaVoxel `.vox` import and export, following
+// https://github.com/ephtracy/voxel-model/blob/master/MagicaVoxel-file-format-vox.txt and
+// https://github.com/ephtracy/voxel-model/blob/master/MagicaVoxel-file-format-vox-extension.txt
+//
+// Palette index `i` (1 to 255) maps to block `i`. Index 0 means "no voxel" in `.vox` files and
+// block 0 is air, so the two line up.
+
+use std::convert::TryInto;
+
+const MAGIC: [u8; 4] = *b"VOX ";
+const VERSION: i32 = 150;
+
+pub struct Vox {
+    pub object: super::Object,
+    pub palette: [[u8; 4]; 256],
+}
+
+#[derive(Debug)]
+pub enum VoxError {
+    Io(std::io::Error),
+    Truncated,
+    BadMagic,
+    Invalid(String),
+}
+
+impl std::fmt::Display for VoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to access .vox file: {}", e),
+            Self::Truncated => write!(f, ".vox file is truncated"),
+            Self::BadMagic => write!(f, "not a .vox file"),
+            Self::Invalid(message) => write!(f, "invalid .vox file: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for VoxError {}
+
+impl From<std::io::Error> for VoxError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+struct Model {
+    size: [i32; 3],
+    voxels: Vec<[u8; 4]>, // x, y, z, palette index
+}
+
+enum Node {
+    Transform {
+        child: i32,
+        rotation: [[i32; 3]; 3],
+        translation: [i32; 3],
+    },
+    Group(Vec<i32>),
+    Shape(Vec<usize>),
+}
+
+#[derive(Copy, Clone)]
+struct Transform {
+    rotation: [[i32; 3]; 3],
+    translation: [i32; 3],
+}
+
+impl Transform {
+    const IDENTITY: Self = Self {
+        rotation: [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+        translation: [0; 3],
+    };
+
+    fn apply(&self, point: [i32; 3]) -> [i32; 3] {
+        let mut result = self.translation;
+        for (row, value) in self.rotation.iter().zip(result.iter_mut()) {
+            *value += row[0] * point[0] + row[1] * point[1] + row[2] * point[2];
+        }
+        result
+    }
+
+    // The transform that applies `child` first, then `self`.
+    fn then(&self, child: &Self) -> Self {
+        let mut rotation = [[0; 3]; 3];
+        for (i, row) in rotation.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..3)
+                    .map(|k| self.rotation[i][k] * child.rotation[k][j])
+                    .sum();
+            }
+        }
+        Self {
+            rotation,
+            translation: self.apply(child.translation),
+        }
+    }
+}
+
+impl Vox {
+    pub fn read<R: std::io::Read>(mut reader: R) -> Result<Self, VoxError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut reader = Reader::new(&bytes);
+
+        if reader.bytes(4)? != MAGIC {
+            return Err(VoxError::BadMagic);
+        }
+        let _version = reader.i32()?;
+
+        let (id, _, mut children) = reader.chunk()?;
+        if id != b"MAIN" {
+            return Err(VoxError::Invalid("missing MAIN chunk".to_string()));
+        }
+
+        let mut palette = default_palette();
+        let mut models = Vec::new();
+        let mut nodes = std::collections::HashMap::new();
+        let mut size = None;
+
+        while !children.is_empty() {
+            let (id, mut content, _) = children.chunk()?;
+            match id {
+                b"SIZE" => size = Some([content.i32()?, content.i32()?, content.i32()?]),
+                b"XYZI" => {
+                    let size = size
+                        .take()
+                        .ok_or_else(|| VoxError::Invalid("XYZI without SIZE".to_string()))?;
+                    let count = content.i32()?;
+                    let voxels = (0..count)
+                        .map(|_| Ok(content.bytes(4)?.try_into().unwrap()))
+                        .collect::<Result<_, VoxError>>()?;
+                    models.push(Model { size, voxels })
+                }
+                b"RGBA" => {
+                    for color in palette.iter_mut().skip(1) {
+                        *color = content.bytes(4)?.try_into().unwrap()
+                    }
+                }
+                b"nTRN" => {
+                    let id = content.i32()?;
+                    content.dict()?;
+                    let child = content.i32()?;
+                    let _reserved = content.i32()?;
+                    let _layer = content.i32()?;
+                    let frames = content.i32()?;
+                    let mut rotation = Transform::IDENTITY.rotation;
+                    let mut translation = [0; 3];
+                    // Only the first frame is used; animation isn't supported.
+                    for frame in 0..frames {
+                        let attributes = content.dict()?;
+                        if frame != 0 {
+                            continue;
+                        }
+                        if let Some(value) = attributes.get("_r") {
+                            rotation = parse_rotation(value)?
+                        }
+                        if let Some(value) = attributes.get("_t") {
+                            translation = parse_translation(value)?
+                        }
+                    }
+                    nodes.insert(
+                        id,
+                        Node::Transform {
+                            child,
+                            rotation,
+                            translation,
+                        },
+                    );
+                }
+                b"nGRP" => {
+                    let id = content.i32()?;
+                    content.dict()?;
+                    let count = content.i32()?;
+                    let children = (0..count)
+                        .map(|_| content.i32())
+                        .collect::<Result<_, _>>()?;
+                    nodes.insert(id, Node::Group(children));
+                }
+                b"nSHP" => {
+                    let id = content.i32()?;
+                    content.dict()?;
+                    let count = content.i32()?;
+                    let mut models = Vec::new();
+                    for _ in 0..count {
+                        models.push(content.i32()? as usize);
+                        content.dict()?;
+                    }
+                    nodes.insert(id, Node::Shape(models));
+                }
+                _ => (),
+            }
+        }
+
+        let mut object = super::Object::new();
+        if nodes.is_empty() {
+            // Files without a scene graph place every model at the origin, uncentered.
+            for model in &models {
+                for &[x, y, z, index] in &model.voxels {
+                    object.set_block([x as i32, y as i32, z as i32], index as super::Block)
+                }
+            }
+        } else {
+            place(&nodes, &models, 0, Transform::IDENTITY, &mut object, 0)?;
+        }
+
+        Ok(Self { object, palette })
+    }
+
+    // Writes one 32x32x32 model per chunk, each positioned by its own transform node. Fails if
+    // the object uses blocks that don't fit in the palette.
+    pub fn write<W: std::io::Write>(
+        object: &super::Object,
+        palette: &[[u8; 4]; 256],
+        mut writer: W,
+    ) -> Result<(), VoxError> {
+        const SIZE: i32 = super::Chunk::SIZE as i32;
+
+        let mut children = Vec::new();
+        let mut shapes = Vec::new();
+        for (coord, chunk) in object.chunks() {
+            let mut voxels = Vec::new();
+            for x in 0..super::Chunk::SIZE {
+                for y in 0..super::Chunk::SIZE {
+                    for z in 0..super::Chunk::SIZE {
+                        let block = chunk.get2(x, y, z);
+                        if block == super::BlockRegistry::AIR {
+                            continue;
+                        }
+                        if block > 255 {
+                            return Err(VoxError::Invalid(format!(
+                                "block {} doesn't fit in the palette",
+                                block
+                            )));
+                        }
+                        voxels.extend_from_slice(&[x as u8, y as u8, z as u8, block as u8]);
+                    }
+                }
+            }
+            if voxels.is_empty() {
+                continue;
+            }
+
+            let mut size = Vec::new();
+            size.extend_from_slice(&SIZE.to_le_bytes());
+            size.extend_from_slice(&SIZE.to_le_bytes());
+            size.extend_from_slice(&SIZE.to_le_bytes());
+            write_chunk(&mut children, b"SIZE", &size);
+
+            let mut xyzi = Vec::new();
+            xyzi.extend_from_slice(&((voxels.len() / 4) as i32).to_le_bytes());
+            xyzi.extend_from_slice(&voxels);
+            write_chunk(&mut children, b"XYZI", &xyzi);
+
+            // Models are centered on their translation.
+            let [x, y, z] = coord.origin();
+            shapes.push([x + SIZE / 2, y + SIZE / 2, z + SIZE / 2]);
+        }
+
+        // Scene graph: transform 0 -> group 1 -> (transform 2i + 2 -> shape 2i + 3) per model.
+        let mut root = Vec::new();
+        write_transform(&mut root, 0, 1, -1, None);
+        write_chunk(&mut children, b"nTRN", &root);
+
+        let mut group = Vec::new();
+        group.extend_from_slice(&1i32.to_le_bytes());
+        write_dict(&mut group, &[]);
+        group.extend_from_slice(&(shapes.len() as i32).to_le_bytes());
+        for i in 0..shapes.len() as i32 {
+            group.extend_from_slice(&(2 * i + 2).to_le_bytes());
+        }
+        write_chunk(&mut children, b"nGRP", &group);
+
+        for (i, translation) in shapes.iter().enumerate() {
+            let i = i as i32;
+            let mut transform = Vec::new();
+            write_transform(&mut transform, 2 * i + 2, 2 * i + 3, 0, Some(*translation));
+            write_chunk(&mut children, b"nTRN", &transform);
+
+            let mut shape = Vec::new();
+            shape.extend_from_slice(&(2 * i + 3).to_le_bytes());
+            write_dict(&mut shape, &[]);
+            shape.extend_from_slice(&1i32.to_le_bytes());
+            shape.extend_from_slice(&i.to_le_bytes());
+            write_dict(&mut shape, &[]);
+            write_chunk(&mut children, b"nSHP", &shape);
+        }
+
+        let mut rgba = Vec::new();
+        for color in palette.iter().skip(1) {
+            rgba.extend_from_slice(color);
+        }
+        rgba.extend_from_slice(&[0; 4]);
+        write_chunk(&mut children, b"RGBA", &rgba);
+
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(b"MAIN")?;
+        writer.write_all(&0i32.to_le_bytes())?;
+        writer.write_all(&(children.len() as i32).to_le_bytes())?;
+        writer.write_all(&children)?;
+        Ok(())
+    }
+
+    pub fn load_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self, VoxError> {
+        Self::read(std::fs::File::open(path)?)
+    }
+
+    pub fn save_file<P: AsRef<std::path::Path>>(
+        object: &super::Object,
+        palette: &[[u8; 4]; 256],
+        path: P,
+    ) -> Result<(), VoxError> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        Self::write(object, palette, &mut writer)?;
+        Ok(std::io::Write::flush(&mut writer)?)
+    }
+
+    pub fn registry(&self) -> super::BlockRegistry {
+        super::BlockRegistry::from_colors(&self.palette)
+    }
+}
+
+fn place(
+    nodes: &std::collections::HashMap<i32, Node>,
+    models: &[Model],
+    id: i32,
+    transform: Transform,
+    object: &mut super::Object,
+    depth: usize,
+) -> Result<(), VoxError> {
+    // Guards against cycles in malformed scene graphs.
+    if depth > nodes.len() {
+        return Err(VoxError::Invalid("scene graph contains a cycle".to_string()));
+    }
+    let node = nodes
+        .get(&id)
+        .ok_or_else(|| VoxError::Invalid(format!("missing scene node {}", id)))?;
+
+    match node {
+        Node::Transform {
+            child,
+            rotation,
+            translation,
+        } => {
+            let local = Transform {
+                rotation: *rotation,
+                translation: *translation,
+            };
+            place(nodes, models, *child, transform.then(&local), object, depth + 1)?
+        }
+        Node::Group(children) => {
+            for &child in children {
+                place(nodes, models, child, transform, object, depth + 1)?
+            }
+        }
+        Node::Shape(shapes) => {
+            for &index in shapes {
+                let model = models
+                    .get(index)
+                    .ok_or_else(|| VoxError::Invalid(format!("missing model {}", index)))?;
+                let [sx, sy, sz] = model.size;
+                for &[x, y, z, block] in &model.voxels {
+                    let centered = [x as i32 - sx / 2, y as i32 - sy / 2, z as i32 - sz / 2];
+                    object.set_block(transform.apply(centered), block as super::Block)
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// The `_r` attribute packs a signed permutation matrix: bits 0-1 and 2-3 are the columns of the
+// non-zero entries of the first and second rows, bits 4-6 are the signs of the three rows.
+fn parse_rotation(value: &str) -> Result<[[i32; 3]; 3], VoxError> {
+    let bits = value
+        .parse::<u8>()
+        .map_err(|_| VoxError::Invalid(format!("invalid rotation `{}`", value)))?;
+    let first = (bits & 3) as usize;
+    let second = (bits >> 2 & 3) as usize;
+    if first > 2 || second > 2 || first == second {
+        return Err(VoxError::Invalid(format!("invalid rotation `{}`", value)));
+    }
+    let columns = [first, second, 3 - first - second];
+
+    let mut rotation = [[0; 3]; 3];
+    for (row, &column) in columns.iter().enumerate() {
+        rotation[row][column] = if bits >> (4 + row) & 1 == 0 { 1 } else { -1 };
+    }
+    Ok(rotation)
+}
+
+fn parse_translation(value: &str) -> Result<[i32; 3], VoxError> {
+    let error = || VoxError::Invalid(format!("invalid translation `{}`", value));
+    let mut parts = value.split_whitespace().map(|part| part.parse::<i32>());
+    let mut translation = [0; 3];
+    for component in translation.iter_mut() {
+        *component = parts.next().ok_or_else(error)?.map_err(|_| error())?;
+    }
+    Ok(translation)
+}
+
+fn write_chunk(out: &mut Vec<u8>, id: &[u8; 4], content: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(content.len() as i32).to_le_bytes());
+    out.extend_from_slice(&0i32.to_le_bytes());
+    out.extend_from_slice(content);
+}
+
+fn write_dict(out: &mut Vec<u8>, entries: &[(&str, &str)]) {
+    out.extend_from_slice(&(entries.len() as i32).to_le_bytes());
+    for string in entries.iter().flat_map(|(key, value)| [key, value]) {
+        out.extend_from_slice(&(string.len() as i32).to_le_bytes());
+        out.extend_from_slice(string.as_bytes());
+    }
+}
+
+fn write_transform(
+    out: &mut Vec<u8>,
+    id: i32,
+    child: i32,
+    layer: i32,
+    translation: Option<[i32; 3]>,
+) {
+    out.extend_from_slice(&id.to_le_bytes());
+    write_dict(out, &[]);
+    out.extend_from_slice(&child.to_le_bytes());
+    out.extend_from_slice(&(-1i32).to_le_bytes());
+    out.extend_from_slice(&layer.to_le_bytes());
+    out.extend_from_slice(&1i32.to_le_bytes());
+    match translation {
+        Some([x, y, z]) => write_dict(out, &[("_t", &format!("{} {} {}", x, y, z))]),
+        None => write_dict(out, &[]),
+    }
+}
+
+// MagicaVoxel's palette for files without an RGBA chunk: a 6x6x6 color cube without black,
+// followed by red, green, blue and gray ramps.
+pub fn default_palette() -> [[u8; 4]; 256] {
+    const CUBE: [u8; 6] = [0xff, 0xcc, 0x99, 0x66, 0x33, 0x00];
+    const RAMP: [u8; 10] = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+
+    let mut colors = Vec::with_capacity(256);
+    colors.push([0, 0, 0, 0]);
+    for &r in &CUBE {
+        for &g in &CUBE {
+            for &b in &CUBE {
+                colors.push([r, g, b, 0xff])
+            }
+        }
+    }
+    colors.pop();
+    colors.extend(RAMP.iter().map(|&v| [v, 0, 0, 0xff]));
+    colors.extend(RAMP.iter().map(|&v| [0, v, 0, 0xff]));
+    colors.extend(RAMP.iter().map(|&v| [0, 0, v, 0xff]));
+    colors.extend(RAMP.iter().map(|&v| [v, v, v, 0xff]));
+    colors.try_into().unwrap()
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], VoxError> {
+        if self.data.len() < count {
+            return Err(VoxError::Truncated);
+        }
+        let (bytes, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, VoxError> {
+        Ok(i32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn length(&mut self) -> Result<usize, VoxError> {
+        let length = self.i32()?;
+        length
+            .try_into()
+            .map_err(|_| VoxError::Invalid(format!("negative length {}", length)))
+    }
+
+    fn string(&mut self) -> Result<String, VoxError> {
+        let length = self.length()?;
+        Ok(String::from_utf8_lossy(self.bytes(length)?).into_owned())
+    }
+
+    fn dict(&mut self) -> Result<std::collections::HashMap<String, String>, VoxError> {
+        let count = self.length()?;
+        (0..count)
+            .map(|_| Ok((self.string()?, self.string()?)))
+            .collect()
+    }
+
+    // Returns the chunk's ID, content and children.
+    fn chunk(&mut self) -> Result<(&'a [u8], Self, Self), VoxError> {
+        let id = self.bytes(4)?;
+        let content_size = self.length()?;
+        let children_size = self.length()?;
+        let content = Self::new(self.bytes(content_size)?);
+        let children = Self::new(self.bytes(children_size)?);
+        Ok((id, content, children))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::{Block, Chunk, Object};
+
+    // Builders for hand-made files, kept independent of `Vox::write`.
+
+    fn le(values: &[i32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    fn chunk(id: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        [&id[..], &le(&[content.len() as i32, 0]), content].concat()
+    }
+
+    fn dict(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut out = le(&[entries.len() as i32]);
+        for string in entries.iter().flat_map(|&(key, value)| [key, value]) {
+            out.extend(le(&[string.len() as i32]));
+            out.extend(string.as_bytes());
+        }
+        out
+    }
+
+    fn file(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let children = chunks.concat();
+        [
+            &b"VOX "[..],
+            &le(&[150]),
+            b"MAIN",
+            &le(&[0, children.len() as i32]),
+            &children,
+        ]
+        .concat()
+    }
+
+    fn size(size: [i32; 3]) -> Vec<u8> {
+        chunk(b"SIZE", &le(&size))
+    }
+
+    fn xyzi(voxels: &[[u8; 4]]) -> Vec<u8> {
+        chunk(
+            b"XYZI",
+            &[le(&[voxels.len() as i32]), voxels.concat()].concat(),
+        )
+    }
+
+    fn rgba(colors: &[[u8; 4]]) -> Vec<u8> {
+        let mut palette = colors.to_vec();
+        palette.resize(256, [0; 4]);
+        chunk(b"RGBA", &palette.concat())
+    }
+
+    fn transform(id: i32, child: i32, attributes: &[(&str, &str)]) -> Vec<u8> {
+        let content = [
+            le(&[id]),
+            dict(&[]),
+            le(&[child, -1, 0, 1]),
+            dict(attributes),
+        ]
+        .concat();
+        chunk(b"nTRN", &content)
+    }
+
+    fn group(id: i32, children: &[i32]) -> Vec<u8> {
+        let content = [
+            le(&[id]),
+            dict(&[]),
+            le(&[children.len() as i32]),
+            le(children),
+        ];
+        chunk(b"nGRP", &content.concat())
+    }
+
+    fn shape(id: i32, model: i32) -> Vec<u8> {
+        chunk(
+            b"nSHP",
+            &[le(&[id]), dict(&[]), le(&[1, model]), dict(&[])].concat(),
+        )
+    }
+
+    // Every non-air voxel of `object`, sorted.
+    fn voxels(object: &Object) -> Vec<([i32; 3], Block)> {
+        let mut voxels = Vec::new();
+        for (coord, chunk) in object.chunks() {
+            let [ox, oy, oz] = coord.origin();
+            for x in 0..Chunk::SIZE {
+                for y in 0..Chunk::SIZE {
+                    for z in 0..Chunk::SIZE {
+                        let block = chunk.get2(x, y, z);
+                        if block != 0 {
+                            voxels.push(([ox + x as i32, oy + y as i32, oz + z as i32], block))
+                        }
+                    }
+                }
+            }
+        }
+        voxels.sort();
+        voxels
+    }
+
+    #[test]
+    fn single_model() {
+        let bytes = file(&[
+            size([2, 2, 2]),
+            xyzi(&[[0, 0, 0, 1], [1, 0, 1, 5]]),
+            rgba(&[
+                [10, 20, 30, 255],
+                [1, 2, 3, 4],
+                [0; 4],
+                [0; 4],
+                [50, 60, 70, 80],
+            ]),
+        ]);
+        let vox = Vox::read(&bytes[..]).unwrap();
+
+        // Without a scene graph, models sit at the origin uncentered.
+        assert_eq!(voxels(&vox.object), vec![([0, 0, 0], 1), ([1, 0, 1], 5)]);
+        // RGBA entry `i` is palette index `i + 1`.
+        assert_eq!(vox.palette[0], [0; 4]);
+        assert_eq!(vox.palette[1], [10, 20, 30, 255]);
+        assert_eq!(vox.palette[2], [1, 2, 3, 4]);
+        assert_eq!(vox.palette[5], [50, 60, 70, 80]);
+        assert_eq!(vox.registry().get(5).color, [50, 60, 70, 80]);
+    }
+
+    #[test]
+    fn default_palette_without_rgba() {
+        let vox = Vox::read(&file(&[size([1, 1, 1]), xyzi(&[[0, 0, 0, 1]])])[..]).unwrap();
+        assert_eq!(vox.palette, default_palette());
+        assert_eq!(vox.palette[1], [0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(vox.palette[255], [0x11, 0x11, 0x11, 0xff]);
+    }
+
+    #[test]
+    fn multiple_models_with_transforms() {
+        // Rotation 33: row 0 takes y, row 1 takes -x, row 2 takes z.
+        let bytes = file(&[
+            size([4, 4, 4]),
+            xyzi(&[[0, 0, 0, 1], [3, 3, 3, 2]]),
+            size([3, 1, 1]),
+            xyzi(&[[2, 0, 0, 7]]),
+            transform(0, 1, &[]),
+            group(1, &[2, 4]),
+            transform(2, 3, &[("_t", "10 0 0")]),
+            shape(3, 0),
+            transform(4, 5, &[("_t", "0 0 -5"), ("_r", "33")]),
+            shape(5, 1),
+        ]);
+        let vox = Vox::read(&bytes[..]).unwrap();
+
+        // Models are centered on their translation: (0, 0, 0) - 4 / 2 + (10, 0, 0).
+        // The second model's voxel is (2, 0, 0) - (1, 0, 0), rotated to (0, -1, 0).
+        assert_eq!(
+            voxels(&vox.object),
+            vec![([0, -1, -5], 7), ([8, -2, -2], 1), ([11, 1, 1], 2)]
+        );
+    }
+
+    #[test]
+    fn nested_transforms() {
+        let bytes = file(&[
+            size([2, 2, 2]),
+            xyzi(&[[1, 1, 1, 3]]),
+            transform(0, 1, &[("_t", "100 0 0")]),
+            transform(1, 2, &[("_t", "0 20 0"), ("_r", "4")]),
+            shape(2, 0),
+        ]);
+        // Rotation 4 is the identity, so the translations just add up.
+        let vox = Vox::read(&bytes[..]).unwrap();
+        assert_eq!(voxels(&vox.object), vec![([100, 20, 0], 3)]);
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = file(&[size([1, 1, 1]), xyzi(&[[0, 0, 0, 1]])]);
+        for length in 0..bytes.len() {
+            assert!(
+                matches!(Vox::read(&bytes[..length]), Err(VoxError::Truncated)),
+                "file cut to {} bytes",
+                length
+            );
+        }
+    }
+
+    #[test]
+    fn corrupt() {
+        let mut bytes = file(&[size([1, 1, 1]), xyzi(&[[0, 0, 0, 1]])]);
+        bytes[0] = b'B';
+        assert!(matches!(Vox::read(&bytes[..]), Err(VoxError::BadMagic)));
+
+        let invalid = |chunks: &[Vec<u8>]| match Vox::read(&file(chunks)[..]) {
+            Err(VoxError::Invalid(_)) => (),
+            Err(e) => panic!("expected an invalid file, got {}", e),
+            Ok(_) => panic!("expected an invalid file"),
+        };
+        invalid(&[xyzi(&[[0, 0, 0, 1]])]);
+        invalid(&[[&b"SIZE"[..], &le(&[-1, 0])].concat()]);
+        invalid(&[
+            size([1, 1, 1]),
+            xyzi(&[]),
+            transform(0, 1, &[]),
+            shape(1, 3),
+        ]);
+        invalid(&[size([1, 1, 1]), xyzi(&[]), transform(0, 1, &[])]);
+        invalid(&[size([1, 1, 1]), xyzi(&[]), transform(0, 0, &[])]);
+        invalid(&[transform(0, 1, &[("_r", "3")]), shape(1, 0)]);
+        invalid(&[transform(0, 1, &[("_t", "1 2")]), shape(1, 0)]);
+
+        // A file whose top-level chunk isn't MAIN.
+        let mut bytes = file(&[]);
+        bytes[8..12].copy_from_slice(b"PACK");
+        assert!(matches!(Vox::read(&bytes[..]), Err(VoxError::Invalid(_))));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut object = Object::new();
+        object.set_block([0, 0, 0], 1);
+        object.set_block([31, 0, 31], 2);
+        object.set_block([-1, -33, 5], 200);
+        object.set_block([40, 70, -90], 255);
+        let mut palette = default_palette();
+        palette[1] = [1, 2, 3, 4];
+        palette[200] = [200, 100, 50, 25];
+        palette[255] = [9, 8, 7, 6];
+
+        let mut bytes = Vec::new();
+        Vox::write(&object, &palette, &mut bytes).unwrap();
+        let vox = Vox::read(&bytes[..]).unwrap();
+        assert_eq!(voxels(&vox.object), voxels(&object));
+        assert_eq!(vox.palette, palette);
+    }
+
+    #[test]
+    fn blocks_outside_the_palette() {
+        let mut object = Object::new();
+        object.set_block([0, 0, 0], 256);
+        assert!(matches!(
+            Vox::write(&object, &default_palette(), Vec::new()),
+            Err(VoxError::Invalid(_))
+        ));
+    }
+}
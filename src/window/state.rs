@@ -12,6 +12,8 @@ pub struct State {
     input_mode: super::InputMode,
 
     key_held: [bool; 255],
+    key_pressed: [bool; 255],
+    mouse_pressed: [bool; 3],
 }
 
 impl State {
@@ -28,6 +30,8 @@ impl State {
             mouse_rel: ultraviolet::Vec2::zero(),
             input_mode,
             key_held: [false; 255],
+            key_pressed: [false; 255],
+            mouse_pressed: [false; 3],
         }
     }
 
@@ -50,18 +54,29 @@ impl State {
                         {
                             self.set_input_mode(window, super::InputMode::MOUSE)
                         }
-                        self.key_held[keycode as usize] =
-                            input.state == winit::event::ElementState::Pressed
+                        let pressed = input.state == winit::event::ElementState::Pressed;
+                        if pressed && !self.key_held[keycode as usize] {
+                            self.key_pressed[keycode as usize] = true
+                        }
+                        self.key_held[keycode as usize] = pressed
                     }
                 }
-                winit::event::WindowEvent::MouseInput { state, button, .. } => {
-                    if button == winit::event::MouseButton::Left
-                        && state == winit::event::ElementState::Pressed
-                        && self.input_mode == super::InputMode::MOUSE
-                    {
-                        self.set_input_mode(window, super::InputMode::CAM3D)
+                winit::event::WindowEvent::MouseInput {
+                    state: winit::event::ElementState::Pressed,
+                    button,
+                    ..
+                } => match self.input_mode {
+                    super::InputMode::MOUSE => {
+                        if button == winit::event::MouseButton::Left {
+                            self.set_input_mode(window, super::InputMode::CAM3D)
+                        }
                     }
-                }
+                    super::InputMode::CAM3D => {
+                        if let Some(index) = Self::mouse_button_index(button) {
+                            self.mouse_pressed[index] = true
+                        }
+                    }
+                },
                 _ => (),
             },
             winit::event::Event::DeviceEvent { event, .. } => match event {
@@ -88,6 +103,15 @@ impl State {
         }
     }
 
+    fn mouse_button_index(button: winit::event::MouseButton) -> Option<usize> {
+        match button {
+            winit::event::MouseButton::Left => Some(0),
+            winit::event::MouseButton::Right => Some(1),
+            winit::event::MouseButton::Middle => Some(2),
+            winit::event::MouseButton::Other(_) => None,
+        }
+    }
+
     fn set_input_mode(&mut self, window: &super::Window, input_mode: super::InputMode) {
         self.input_mode = input_mode;
         window.set_input_mode(input_mode)
@@ -95,7 +119,9 @@ impl State {
 
     pub fn reset(&mut self) {
         self.main = false;
-        self.mouse_rel = ultraviolet::Vec2::zero()
+        self.mouse_rel = ultraviolet::Vec2::zero();
+        self.key_pressed = [false; 255];
+        self.mouse_pressed = [false; 3]
     }
 
     pub fn main(&self) -> bool {
@@ -119,4 +145,12 @@ impl State {
     pub fn key_held(&self, key: winit::event::VirtualKeyCode) -> bool {
         self.key_held[key as usize]
     }
+    pub fn key_pressed(&self, key: winit::event::VirtualKeyCode) -> bool {
+        self.key_pressed[key as usize]
+    }
+
+    // Only counts clicks made while the camera is being controlled.
+    pub fn mouse_pressed(&self, button: winit::event::MouseButton) -> bool {
+        Self::mouse_button_index(button).is_some_and(|index| self.mouse_pressed[index])
+    }
 }
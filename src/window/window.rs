@@ -1,6 +1,7 @@
 pub struct Window {
     event_loop: Option<winit::event_loop::EventLoop<()>>,
     window: winit::window::Window,
+    frame_time: Option<std::time::Duration>,
 }
 
 impl Window {
@@ -15,9 +16,15 @@ impl Window {
         Self {
             event_loop: Some(event_loop),
             window,
+            frame_time: None,
         }
     }
 
+    // Caps how often `run` calls its input handler, or lets it run as fast as it can with `None`.
+    pub fn set_max_fps(&mut self, max_fps: Option<std::num::NonZeroU32>) {
+        self.frame_time = max_fps.map(|max_fps| std::time::Duration::from_secs(1) / max_fps.get());
+    }
+
     pub fn window(&self) -> &winit::window::Window {
         &self.window
     }
@@ -55,8 +62,22 @@ impl Window {
         };
 
         let mut state = super::State::new(&self);
+        let mut next_frame = std::time::Instant::now();
 
         event_loop.run(move |event, _, control_flow| {
+            // With a frame cap, sleep until the next frame is due instead of polling, and hide
+            // early `MainEventsCleared` events from `state` so frame times stay right.
+            if let (winit::event::Event::MainEventsCleared, Some(frame_time)) =
+                (&event, self.frame_time)
+            {
+                let now = std::time::Instant::now();
+                if now < next_frame {
+                    *control_flow = winit::event_loop::ControlFlow::WaitUntil(next_frame);
+                    return;
+                }
+                next_frame = (next_frame + frame_time).max(now);
+                *control_flow = winit::event_loop::ControlFlow::WaitUntil(next_frame)
+            }
             state.handle_event(&self, event);
             if state.quit() {
                 *control_flow = winit::event_loop::ControlFlow::Exit
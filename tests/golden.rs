@@ -0,0 +1,59 @@
+// Runs the golden-image check of the `voxelite` binary against the references in tests/golden.
+// Each renderer has its own: the software renderer samples only full-size textures and traces
+// exact shadows where the GPU filters a shadow map, so their images can't match each other. The
+// references are checked in, so a missing one fails the check instead of being written. After an
+// intended rendering change, regenerate them with
+//
+//   cargo run -- --golden tests/golden/cpu --bless --cpu
+//   VK_ICD_FILENAMES=/usr/share/vulkan/icd.d/lvp_icd.x86_64.json \
+//       cargo run -- --golden tests/golden/vulkan --bless
+
+const CPU_REFERENCES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/cpu");
+const VULKAN_REFERENCES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/vulkan");
+
+fn golden(dir: &std::path::Path, args: &[&str]) -> bool {
+    std::process::Command::new(env!("CARGO_BIN_EXE_voxelite"))
+        .arg("--golden")
+        .arg(dir)
+        .args(args)
+        .status()
+        .expect("failed to run voxelite")
+        .success()
+}
+
+#[test]
+fn software_renderer() {
+    assert!(
+        golden(CPU_REFERENCES.as_ref(), &["--cpu"]),
+        "golden images differ, see the .actual.ppm and .diff.ppm files in {}",
+        CPU_REFERENCES
+    );
+}
+
+// Needs a Vulkan driver. Run it on lavapipe, which the references were blessed with, so the output
+// doesn't depend on the GPU:
+//
+//   VK_ICD_FILENAMES=/usr/share/vulkan/icd.d/lvp_icd.x86_64.json cargo test -- --ignored
+#[test]
+#[ignore]
+fn vulkan_renderer() {
+    assert!(
+        std::path::Path::new(VULKAN_REFERENCES).is_dir(),
+        "no Vulkan references in {}, bless them on lavapipe first",
+        VULKAN_REFERENCES
+    );
+    assert!(
+        golden(VULKAN_REFERENCES.as_ref(), &[]),
+        "golden images differ, see the .actual.ppm and .diff.ppm files in {}",
+        VULKAN_REFERENCES
+    );
+}
+
+#[test]
+fn missing_references_fail() {
+    let dir = std::env::temp_dir().join(format!("voxelite-golden-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let passed = golden(&dir, &["--cpu"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(!passed, "the check passed without references");
+}